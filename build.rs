@@ -0,0 +1,45 @@
+//! Extract the layout of the `Globals` struct from `src/globals.wgsl`, so that
+//! it can be checked against the Rust struct at compile time.
+use std::fmt::Write;
+use std::path::PathBuf;
+
+const SHADER: &str = "src/globals.wgsl";
+
+fn main() {
+    println!("cargo::rerun-if-changed={SHADER}");
+    let source = std::fs::read_to_string(SHADER).unwrap();
+    let module = naga::front::wgsl::parse_str(&source)
+        .unwrap_or_else(|err| panic!("{}", err.emit_to_string_with_path(&source, SHADER)));
+    let mut layouter = naga::proc::Layouter::default();
+    layouter.update(module.to_ctx()).unwrap();
+
+    let (members, span) = module
+        .types
+        .iter()
+        .find_map(|(_, ty)| match &ty.inner {
+            naga::TypeInner::Struct { members, span } if ty.name.as_deref() == Some("Globals") => {
+                Some((members, span))
+            }
+            _ => None,
+        })
+        .unwrap_or_else(|| panic!("no `Globals` struct in {SHADER}"));
+
+    let mut out = String::new();
+    writeln!(out, "/// The size of `Globals` in WGSL.").unwrap();
+    writeln!(out, "const WGSL_SIZE: usize = {span};").unwrap();
+    writeln!(
+        out,
+        "/// The name, offset and size of each member of `Globals` in WGSL."
+    )
+    .unwrap();
+    writeln!(out, "const WGSL_MEMBERS: &[(&str, usize, usize)] = &[").unwrap();
+    for member in members {
+        let name = member.name.as_deref().unwrap();
+        let size = layouter[member.ty].size;
+        writeln!(out, "    ({name:?}, {}, {size}),", member.offset).unwrap();
+    }
+    writeln!(out, "];").unwrap();
+
+    let out_dir = PathBuf::from(std::env::var_os("OUT_DIR").unwrap());
+    std::fs::write(out_dir.join("globals_layout.rs"), out).unwrap();
+}
@@ -0,0 +1,33 @@
+//! Negotiating how the surface is composited with the content behind it.
+
+use wgpu::CompositeAlphaMode;
+
+/// Pick an alpha mode that is supported by the surface.
+///
+/// If `transparent`, prefer a mode that composites the surface using its
+/// alpha channel, otherwise prefer one that ignores it.
+pub fn negotiate(transparent: bool, supported: &[CompositeAlphaMode]) -> CompositeAlphaMode {
+    let preferences: &[_] = if transparent {
+        &[
+            CompositeAlphaMode::PreMultiplied,
+            CompositeAlphaMode::PostMultiplied,
+            CompositeAlphaMode::Inherit,
+        ]
+    } else {
+        &[CompositeAlphaMode::Opaque, CompositeAlphaMode::Inherit]
+    };
+    if let Some(&alpha_mode) = preferences.iter().find(|mode| supported.contains(mode)) {
+        return alpha_mode;
+    }
+    let alpha_mode = supported
+        .first()
+        .copied()
+        .unwrap_or(CompositeAlphaMode::Auto);
+    tracing::warn!(
+        transparent,
+        ?supported,
+        ?alpha_mode,
+        "no preferred alpha mode is supported, falling back"
+    );
+    alpha_mode
+}
@@ -5,16 +5,19 @@ use objc2::runtime::ProtocolObject;
 use objc2::{define_class, msg_send, DefinedClass, MainThreadMarker, MainThreadOnly};
 use objc2_app_kit::{
     NSApplication, NSApplicationActivationPolicy, NSApplicationDelegate, NSBackingStoreType,
-    NSStackView, NSStackViewDistribution, NSUserInterfaceLayoutOrientation, NSWindow,
+    NSColor, NSStackView, NSStackViewDistribution, NSUserInterfaceLayoutOrientation, NSWindow,
     NSWindowStyleMask,
 };
 use objc2_core_foundation::{CGPoint, CGRect, CGSize};
 use objc2_foundation::{NSNotification, NSObject, NSObjectProtocol, NSPoint, NSRect, NSSize};
 
+use crate::config::Config;
+use crate::trace_recorder;
 use crate::view::WgpuTriangleView;
 
 #[derive(Debug)]
 struct Ivars {
+    config: Config,
     window: OnceCell<Retained<NSWindow>>,
 }
 
@@ -43,12 +46,21 @@ define_class!(
             tracing::info!("applicationShouldTerminateAfterLastWindowClosed:");
             true
         }
+
+        #[unsafe(method(applicationWillTerminate:))]
+        fn will_terminate(&self, _notification: &NSNotification) {
+            tracing::info!("applicationWillTerminate:");
+            if let Err(err) = trace_recorder::finish() {
+                tracing::error!(%err, "failed finishing trace");
+            }
+        }
     }
 );
 
 impl Delegate {
-    fn new(mtm: MainThreadMarker) -> Retained<Self> {
+    fn new(mtm: MainThreadMarker, config: Config) -> Retained<Self> {
         let this = mtm.alloc().set_ivars(Ivars {
+            config,
             window: OnceCell::new(),
         });
         unsafe { msg_send![super(this), init] }
@@ -82,24 +94,40 @@ impl Delegate {
         // Important for memory safety!
         unsafe { window.setReleasedWhenClosed(false) };
 
-        if cfg!(feature = "two-triangles") {
+        let config = self.ivars().config;
+        if config.transparent {
+            // Composite the window with what is behind it.
+            window.setOpaque(false);
+            window.setBackgroundColor(Some(&NSColor::clearColor()));
+        }
+        let views = if config.two_triangles {
             // Frame will be resized by NSStackView automatically
             let frame = CGRect::new(CGPoint::new(0.0, 0.0), CGSize::new(1.0, 1.0));
+            let views = vec![
+                WgpuTriangleView::new(mtm, frame, config),
+                WgpuTriangleView::new(mtm, frame, config),
+            ];
             let view = NSStackView::new(mtm);
-            view.addArrangedSubview(&WgpuTriangleView::new(mtm, frame));
-            view.addArrangedSubview(&WgpuTriangleView::new(mtm, frame));
+            for triangle_view in &views {
+                view.addArrangedSubview(triangle_view);
+            }
             view.setOrientation(NSUserInterfaceLayoutOrientation::Horizontal);
             view.setDistribution(NSStackViewDistribution::FillEqually);
             window.setContentView(Some(&view));
+            views
         } else {
             let frame = window.contentView().expect("window content view").frame();
-            let view = WgpuTriangleView::new(mtm, frame);
+            let view = WgpuTriangleView::new(mtm, frame, config);
             window.setContentView(Some(&view));
-        }
+            vec![view]
+        };
 
         window.center();
         window.makeKeyAndOrderFront(None);
 
+        // Show the window first, and set up the renderers afterwards.
+        WgpuTriangleView::install_renderers_in_background(views, config);
+
         self.ivars()
             .window
             .set(window)
@@ -107,9 +135,9 @@ impl Delegate {
     }
 }
 
-pub fn main(mtm: MainThreadMarker) {
+pub fn main(mtm: MainThreadMarker, config: Config) {
     let app = NSApplication::sharedApplication(mtm);
-    let delegate = Delegate::new(mtm);
+    let delegate = Delegate::new(mtm, config);
     app.setDelegate(Some(ProtocolObject::from_ref(&*delegate)));
     app.run();
 }
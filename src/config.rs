@@ -0,0 +1,333 @@
+//! Runtime selection of the mode of execution.
+use std::fmt;
+
+use crate::present_mode;
+use crate::surface_format::FormatPreference;
+
+/// The different modes of execution, used to compare their behaviour.
+///
+/// Each option can be enabled with a command-line flag (e.g.
+/// `--immediate-redraw`), or with an environment variable (e.g.
+/// `WGPU_OBJC2_EXAMPLE_IMMEDIATE_REDRAW=1`). The options that take a value
+/// are set in the same way, e.g. with `--frame-latency=2` or
+/// `WGPU_OBJC2_EXAMPLE_FRAME_LATENCY=2`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Config {
+    /// Redraw immediately when resizing or when stepping.
+    pub immediate_redraw: bool,
+    /// Set the view as needing display immediately after drawing.
+    pub queue_display: bool,
+    /// Trigger `drawRect:` instead of `updateLayer`.
+    ///
+    /// Only relevant in AppKit - this is always enabled when using UIKit.
+    pub draw_rect: bool,
+    /// Request redraws using `CADisplayLink`.
+    pub display_link: bool,
+    /// Disable VSync, see [`present_mode::NO_VSYNC`].
+    pub no_vsync: bool,
+    /// Show two triangles side by side instead of just one.
+    pub two_triangles: bool,
+    /// Recompile `src/shader.wgsl` whenever it changes on disk.
+    ///
+    /// Only available in debug builds.
+    pub hot_reload_shader: bool,
+    /// Render in extended range (`Rgba16Float`) if the surface supports it.
+    pub extended_range: bool,
+    /// Clear to a translucent color, and composite the view with the content
+    /// behind it.
+    pub transparent: bool,
+    /// Record a Chrome trace of each frame, see [`Config::trace_path`].
+    pub trace: bool,
+    /// The maximum number of frames queued up for presentation, or `None` to
+    /// use the surface's default.
+    pub frame_latency: Option<u32>,
+    /// The number of samples per pixel to smooth the edges of the triangle
+    /// with, or `None` to not multisample.
+    ///
+    /// Lowered to what the adapter supports when creating the renderer.
+    pub msaa: Option<u32>,
+}
+
+/// The options, along with their description.
+const OPTIONS: &[(&str, &str)] = &[
+    (
+        "immediate-redraw",
+        "Redraw immediately when resizing or when stepping.",
+    ),
+    (
+        "queue-display",
+        "Set the view as needing display immediately after drawing.",
+    ),
+    (
+        "draw-rect",
+        "Trigger `drawRect:` instead of `updateLayer` (AppKit only).",
+    ),
+    ("display-link", "Request redraws using `CADisplayLink`."),
+    (
+        "no-vsync",
+        "Disable VSync (use Mailbox, Immediate or FifoRelaxed if supported).",
+    ),
+    (
+        "two-triangles",
+        "Show two triangles side by side instead of just one.",
+    ),
+    (
+        "hot-reload-shader",
+        "Recompile `src/shader.wgsl` whenever it changes (debug builds only).",
+    ),
+    (
+        "extended-range",
+        "Render in extended range (`Rgba16Float`) if supported.",
+    ),
+    (
+        "transparent",
+        "Clear to a translucent color, showing what is behind the window.",
+    ),
+    (
+        "trace",
+        "Record a trace of each frame to `trace.json`, to view in Perfetto.",
+    ),
+];
+
+/// The options that take a value, along with their description.
+const VALUE_OPTIONS: &[(&str, &str)] = &[
+    (
+        "frame-latency",
+        "The maximum number of frames queued up for presentation.",
+    ),
+    (
+        "msaa",
+        "Smooth edges with N (1, 2, 4, 8 or 16) samples per pixel, if supported.",
+    ),
+];
+
+const ENV_PREFIX: &str = "WGPU_OBJC2_EXAMPLE_";
+
+/// An error while parsing the configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// `--help` was passed.
+    Help,
+    UnknownFlag(String),
+    /// An option that takes a value was passed without one, e.g.
+    /// `--frame-latency` instead of `--frame-latency=2`.
+    MissingValue(String),
+    InvalidEnvValue {
+        name: String,
+        value: String,
+    },
+    InvalidFrameLatency(String),
+    InvalidSampleCount(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Help => write!(f, "help requested"),
+            Self::UnknownFlag(flag) => write!(f, "unknown flag {flag:?}"),
+            Self::MissingValue(option) => {
+                write!(f, "missing value for --{option}, expected --{option}=N")
+            }
+            Self::InvalidEnvValue { name, value } => {
+                write!(f, "invalid value {value:?} for ${name}, expected 0 or 1")
+            }
+            Self::InvalidFrameLatency(value) => {
+                write!(
+                    f,
+                    "invalid frame latency {value:?}, expected a positive integer"
+                )
+            }
+            Self::InvalidSampleCount(value) => {
+                write!(
+                    f,
+                    "invalid sample count {value:?}, expected 1, 2, 4, 8 or 16"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Load the configuration from the process' arguments and environment.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::parse(std::env::args().skip(1), |name| std::env::var(name).ok())
+    }
+
+    /// Load the configuration from the given arguments (excluding the
+    /// program name) and environment variable lookup function.
+    ///
+    /// Command-line flags enable options in addition to the ones enabled by
+    /// environment variables.
+    pub fn parse(
+        args: impl IntoIterator<Item = String>,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, ConfigError> {
+        let mut config = Self::default();
+
+        for (option, _) in OPTIONS {
+            let name = env_name(option);
+            if let Some(value) = env(&name) {
+                let enabled = match &*value {
+                    "1" | "true" | "yes" => true,
+                    "" | "0" | "false" | "no" => false,
+                    _ => return Err(ConfigError::InvalidEnvValue { name, value }),
+                };
+                *config.option_mut(option) |= enabled;
+            }
+        }
+        for (option, _) in VALUE_OPTIONS {
+            if let Some(value) = env(&env_name(option)) {
+                config.set_value(option, value)?;
+            }
+        }
+
+        for arg in args {
+            if arg == "--help" || arg == "-h" {
+                return Err(ConfigError::Help);
+            }
+            if let Some((option, value)) = arg
+                .strip_prefix("--")
+                .and_then(|flag| flag.split_once('='))
+                .filter(|(option, _)| VALUE_OPTIONS.iter().any(|(name, _)| name == option))
+            {
+                config.set_value(option, value.to_string())?;
+                continue;
+            }
+            if let Some((option, _)) = arg
+                .strip_prefix("--")
+                .and_then(|flag| VALUE_OPTIONS.iter().find(|(option, _)| *option == flag))
+            {
+                return Err(ConfigError::MissingValue(option.to_string()));
+            }
+            let option = arg
+                .strip_prefix("--")
+                .and_then(|flag| OPTIONS.iter().find(|(option, _)| *option == flag))
+                .ok_or_else(|| ConfigError::UnknownFlag(arg.clone()))?;
+            *config.option_mut(option.0) = true;
+        }
+
+        Ok(config)
+    }
+
+    /// A description of the available options.
+    pub fn usage() -> String {
+        let mut usage = String::from("Options:\n");
+        for (option, description) in OPTIONS {
+            usage += &format!("  --{option:<18}{description}\n");
+        }
+        for (option, description) in VALUE_OPTIONS {
+            usage += &format!("  --{:<18}{description}\n", format!("{option}=<N>"));
+        }
+        usage += &format!(
+            "\nEach option can also be enabled with an environment variable, e.g. {ENV_PREFIX}NO_VSYNC=1.\n"
+        );
+        usage
+    }
+
+    /// The present modes to try to configure the surface with, in order of
+    /// preference.
+    pub fn present_modes(&self) -> &'static [wgpu::PresentMode] {
+        if self.no_vsync {
+            present_mode::NO_VSYNC
+        } else {
+            present_mode::VSYNC
+        }
+    }
+
+    /// The number of samples per pixel to render with.
+    pub fn sample_count(&self) -> u32 {
+        self.msaa.unwrap_or(1)
+    }
+
+    /// Which kind of format to configure the surface with.
+    pub fn format_preference(&self) -> FormatPreference {
+        if self.extended_range {
+            FormatPreference::ExtendedRange
+        } else {
+            FormatPreference::Srgb
+        }
+    }
+
+    /// The color to clear the view with.
+    pub fn clear_color(&self) -> wgpu::Color {
+        if self.transparent {
+            wgpu::Color {
+                a: 0.5,
+                ..wgpu::Color::GREEN
+            }
+        } else {
+            wgpu::Color::GREEN
+        }
+    }
+
+    /// The shader file to watch, if hot-reloading is enabled.
+    pub fn shader_path(&self) -> Option<&'static str> {
+        self.hot_reload_shader
+            .then_some(concat!(env!("CARGO_MANIFEST_DIR"), "/src/shader.wgsl"))
+    }
+
+    /// The file to record a trace to, if tracing is enabled.
+    ///
+    /// The trace can be viewed in Perfetto, see [`trace_recorder`].
+    ///
+    /// [`trace_recorder`]: crate::trace_recorder
+    pub fn trace_path(&self) -> Option<&'static str> {
+        self.trace.then_some("trace.json")
+    }
+
+    /// Whether to redraw immediately after the view was resized.
+    ///
+    /// Not needed when using a display link, since that will trigger a
+    /// redraw soon anyhow.
+    pub fn redraw_on_resize(&self) -> bool {
+        self.immediate_redraw && !self.display_link
+    }
+
+    fn option_mut(&mut self, option: &str) -> &mut bool {
+        match option {
+            "immediate-redraw" => &mut self.immediate_redraw,
+            "queue-display" => &mut self.queue_display,
+            "draw-rect" => &mut self.draw_rect,
+            "display-link" => &mut self.display_link,
+            "no-vsync" => &mut self.no_vsync,
+            "two-triangles" => &mut self.two_triangles,
+            "hot-reload-shader" => &mut self.hot_reload_shader,
+            "extended-range" => &mut self.extended_range,
+            "transparent" => &mut self.transparent,
+            "trace" => &mut self.trace,
+            _ => unreachable!("unknown option {option}"),
+        }
+    }
+
+    fn set_value(&mut self, option: &str, value: String) -> Result<(), ConfigError> {
+        match option {
+            "frame-latency" => self.frame_latency = Some(parse_frame_latency(value)?),
+            "msaa" => self.msaa = parse_sample_count(value)?,
+            _ => unreachable!("unknown option {option}"),
+        }
+        Ok(())
+    }
+}
+
+/// The environment variable that sets `option`.
+fn env_name(option: &str) -> String {
+    format!("{ENV_PREFIX}{}", option.replace('-', "_").to_uppercase())
+}
+
+fn parse_frame_latency(value: String) -> Result<u32, ConfigError> {
+    match value.parse() {
+        Ok(latency) if latency > 0 => Ok(latency),
+        _ => Err(ConfigError::InvalidFrameLatency(value)),
+    }
+}
+
+/// `None` for a single sample, i.e. without multisampling.
+fn parse_sample_count(value: String) -> Result<Option<u32>, ConfigError> {
+    match value.parse() {
+        Ok(1) => Ok(None),
+        Ok(count @ (2 | 4 | 8 | 16)) => Ok(Some(count)),
+        _ => Err(ConfigError::InvalidSampleCount(value)),
+    }
+}
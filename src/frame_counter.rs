@@ -1,23 +1,62 @@
 use std::{
-    sync::{Arc, Mutex},
+    collections::VecDeque,
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex,
+    },
     thread::{self, JoinHandle},
     time::{Duration, Instant},
 };
 
+use crate::stats_sink::{FrameStats, StatsSink, TracingSink};
+
+/// How many frame intervals to keep, enough for a second at 1000 FPS.
+const FRAME_TIMES_CAPACITY: usize = 1000;
+
+/// Frames that take longer than this many refresh intervals are hitches.
+const HITCH_FACTOR: f64 = 1.5;
+
+/// Frame intervals longer than this are the renderer idling between redraws
+/// on demand, rather than slow frames, and are left out of the statistics.
+const IDLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The refresh interval to assume until told otherwise, i.e. 60 Hz.
+const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_nanos(16_666_667);
+
+/// How often to report the statistics, unless told otherwise.
+const DEFAULT_REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often the reporter thread checks whether a report is due.
+const REPORTER_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 #[derive(Debug)]
 pub struct FrameCounter {
     state: Arc<Mutex<FrameCounterState>>,
-    // Helper thread to ensure that we print the FPS every second, even if it's 0.
-    printer_thread: Option<JoinHandle<()>>,
+    // Only used by the reporter thread, and separate from `state`, so that
+    // recording a frame never waits for a sink to write its report.
+    sink: Arc<Mutex<Box<dyn StatsSink>>>,
+    // Dropped to stop the reporter thread.
+    stop: Option<mpsc::Sender<()>>,
+    // Helper thread that reports every second, including when no frames
+    // were rendered.
+    reporter_thread: Option<JoinHandle<()>>,
 }
 
 impl Drop for FrameCounter {
     fn drop(&mut self) {
-        self.printer_thread
+        // Disconnecting the channel wakes up the reporter thread immediately.
+        drop(self.stop.take());
+        self.reporter_thread
             .take()
-            .expect("printer thread set")
+            .expect("reporter thread set")
             .join()
-            .expect("printer thread");
+            .expect("reporter thread");
+    }
+}
+
+impl Default for FrameCounter {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -25,45 +64,229 @@ impl Drop for FrameCounter {
 struct FrameCounterState {
     last_printed_instant: Instant,
     frame_count: u32,
+    last_frame_instant: Option<Instant>,
+    frame_times: FrameTimes,
+    // The number of intervals recorded since the last print, which is lower
+    // than `frame_count` when idling.
+    interval_count: usize,
+    refresh_interval: Duration,
+    report_interval: Duration,
+    // Printed along with the FPS, to compare latency against throughput.
+    frame_latency: Option<u32>,
+    // The GPU time of the frames measured since the last print.
+    gpu_time: Duration,
+    gpu_frame_count: u32,
 }
 
 impl FrameCounter {
+    /// Create a counter that logs the statistics with `tracing`.
     pub fn new() -> Self {
+        Self::with_sink(Box::new(TracingSink))
+    }
+
+    /// Create a counter that reports the statistics to `sink`.
+    pub fn with_sink(sink: Box<dyn StatsSink>) -> Self {
         let state = FrameCounterState {
             last_printed_instant: Instant::now(),
             frame_count: 0,
+            last_frame_instant: None,
+            frame_times: FrameTimes::new(FRAME_TIMES_CAPACITY),
+            interval_count: 0,
+            refresh_interval: DEFAULT_REFRESH_INTERVAL,
+            report_interval: DEFAULT_REPORT_INTERVAL,
+            frame_latency: None,
+            gpu_time: Duration::ZERO,
+            gpu_frame_count: 0,
         };
         let state: Arc<Mutex<FrameCounterState>> = Arc::new(Mutex::new(state));
+        let sink = Arc::new(Mutex::new(sink));
         let state_clone = Arc::clone(&state);
+        let sink_clone = Arc::clone(&sink);
+        let (stop, stop_receiver) = mpsc::channel::<()>();
         Self {
             state,
-            printer_thread: Some(thread::spawn(move || loop {
-                state_clone.lock().unwrap().print();
-                thread::sleep(Duration::from_millis(100));
+            sink,
+            stop: Some(stop),
+            reporter_thread: Some(thread::spawn(move || {
+                // Returns early when stopped.
+                while let Err(RecvTimeoutError::Timeout) =
+                    stop_receiver.recv_timeout(REPORTER_POLL_INTERVAL)
+                {
+                    // Released before reporting, which may write to a file.
+                    let stats = state_clone.lock().unwrap().take_stats();
+                    if let Some(stats) = stats {
+                        sink_clone.lock().unwrap().report(&stats);
+                    }
+                }
             })),
         }
     }
 
+    /// Report the statistics to `sink` from now on.
+    pub fn set_sink(&self, sink: Box<dyn StatsSink>) {
+        *self.sink.lock().unwrap() = sink;
+    }
+
     pub fn update(&self) {
         self.state.lock().unwrap().update();
     }
+
+    /// A callback to record how long the GPU spent on a frame, which may be
+    /// called from any thread.
+    pub fn gpu_time_recorder(&self) -> impl FnOnce(Duration) + Send + 'static {
+        let state = Arc::clone(&self.state);
+        move |gpu_time| {
+            let mut state = state.lock().unwrap();
+            state.gpu_time += gpu_time;
+            state.gpu_frame_count += 1;
+        }
+    }
+
+    pub fn set_frame_latency(&self, frame_latency: Option<u32>) {
+        self.state.lock().unwrap().frame_latency = frame_latency;
+    }
+
+    /// Set the refresh interval of the display, which is used to detect
+    /// hitches.
+    pub fn set_refresh_interval(&self, refresh_interval: Duration) {
+        self.state.lock().unwrap().refresh_interval = refresh_interval;
+    }
+
+    /// Set how often the statistics are reported, one second by default.
+    ///
+    /// Reports are made by a helper thread that checks every 100
+    /// milliseconds whether one is due, so the interval is rounded up to that.
+    pub fn set_report_interval(&self, report_interval: Duration) {
+        self.state.lock().unwrap().report_interval = report_interval;
+    }
 }
 
 impl FrameCounterState {
     fn update(&mut self) {
+        let now = Instant::now();
+        if let Some(last_frame_instant) = self.last_frame_instant {
+            let interval = now - last_frame_instant;
+            if interval > IDLE_INTERVAL {
+                tracing::trace!(?interval, "redrawing after idling");
+            } else {
+                if is_hitch(interval, self.refresh_interval) {
+                    tracing::debug!(?interval, refresh_interval = ?self.refresh_interval, "hitch");
+                }
+                self.frame_times.push(interval);
+                self.interval_count += 1;
+            }
+        }
+        self.last_frame_instant = Some(now);
         self.frame_count += 1;
-        self.print();
     }
 
-    fn print(&mut self) {
+    /// The statistics since the last report, if a report is due, after which
+    /// they are reset.
+    fn take_stats(&mut self) -> Option<FrameStats> {
         let now = Instant::now();
         let elapsed = now - self.last_printed_instant;
-        if elapsed > Duration::from_secs(1) {
-            let fps = self.frame_count as f32 / elapsed.as_secs_f32();
-            tracing::info!("FPS: {:.1}", fps);
+        if elapsed <= self.report_interval {
+            return None;
+        }
+        let fps = self.frame_count as f32 / elapsed.as_secs_f32();
+        // Only known if the GPU supports timestamp queries.
+        let gpu_ms = (self.gpu_frame_count > 0)
+            .then(|| self.gpu_time.as_secs_f32() * 1000.0 / self.gpu_frame_count as f32);
+        let frame_times = self
+            .frame_times
+            .stats(self.interval_count, self.refresh_interval);
+        let stats = FrameStats {
+            elapsed,
+            frame_count: self.frame_count,
+            fps,
+            frame_latency: self.frame_latency,
+            gpu_ms,
+            frame_times,
+        };
+
+        self.last_printed_instant = now;
+        self.frame_count = 0;
+        self.interval_count = 0;
+        self.gpu_time = Duration::ZERO;
+        self.gpu_frame_count = 0;
+        Some(stats)
+    }
+}
+
+/// Whether a frame interval is long enough to be noticeable as judder.
+fn is_hitch(interval: Duration, refresh_interval: Duration) -> bool {
+    interval.as_secs_f64() > refresh_interval.as_secs_f64() * HITCH_FACTOR
+}
+
+/// A ring buffer of the most recent frame intervals.
+#[derive(Debug, Clone)]
+pub struct FrameTimes {
+    intervals: VecDeque<Duration>,
+    capacity: usize,
+}
 
-            self.last_printed_instant = now;
-            self.frame_count = 0;
+/// Statistics over a number of frame intervals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameTimeStats {
+    pub min: Duration,
+    pub avg: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+    /// The number of intervals that took more than 1.5 refresh intervals.
+    pub hitches: usize,
+}
+
+impl FrameTimes {
+    /// Keep up to `capacity` intervals.
+    ///
+    /// # Panics
+    ///
+    /// If `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "frame times must have a non-zero capacity");
+        Self {
+            intervals: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Record an interval, forgetting the oldest one if full.
+    pub fn push(&mut self, interval: Duration) {
+        if self.intervals.len() == self.capacity {
+            self.intervals.pop_front();
         }
+        self.intervals.push_back(interval);
+    }
+
+    /// Compute statistics over (up to) the `last` most recent intervals.
+    ///
+    /// Returns `None` if no intervals have been recorded.
+    pub fn stats(&self, last: usize, refresh_interval: Duration) -> Option<FrameTimeStats> {
+        let skip = self.intervals.len().saturating_sub(last);
+        let mut sorted: Vec<Duration> = self.intervals.iter().skip(skip).copied().collect();
+        if sorted.is_empty() {
+            return None;
+        }
+        sorted.sort_unstable();
+
+        // Nearest-rank percentile.
+        let percentile = |p: f64| {
+            let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+            sorted[rank.clamp(1, sorted.len()) - 1]
+        };
+        Some(FrameTimeStats {
+            min: sorted[0],
+            avg: sorted.iter().sum::<Duration>() / sorted.len() as u32,
+            p50: percentile(50.0),
+            p95: percentile(95.0),
+            p99: percentile(99.0),
+            max: sorted[sorted.len() - 1],
+            hitches: sorted
+                .iter()
+                .filter(|&&interval| is_hitch(interval, refresh_interval))
+                .count(),
+        })
     }
 }
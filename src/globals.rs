@@ -0,0 +1,96 @@
+//! The uniforms that are available to every shader.
+use std::mem::offset_of;
+
+/// The source of the WGSL `Globals` struct, and the `globals` uniform that
+/// is bound to group 0.
+pub const WGSL: &str = include_str!("globals.wgsl");
+
+/// The per-frame built-ins, bound as `globals` in group 0 of every scene's
+/// shaders.
+///
+/// See `globals.wgsl` for the meaning of each member.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Globals {
+    pub time: f32,
+    pub delta_time: f32,
+    pub frame: u32,
+    pub scale_factor: f32,
+    pub resolution: [f32; 2],
+    pub logical_resolution: [f32; 2],
+    pub pointer: [f32; 2],
+}
+
+impl Globals {
+    /// The value of `pointer` when the pointer is not over the view.
+    pub const NO_POINTER: [f32; 2] = [-1.0, -1.0];
+}
+
+// Generated by the build script from `globals.wgsl`.
+include!(concat!(env!("OUT_DIR"), "/globals_layout.rs"));
+
+const fn field_size<T>(_field: fn(&Globals) -> &T) -> usize {
+    size_of::<T>()
+}
+
+/// The name, offset and size of each member of the given fields.
+macro_rules! members {
+    ($($field:ident),* $(,)?) => {
+        [$((
+            stringify!($field),
+            offset_of!(Globals, $field),
+            field_size(|globals| &globals.$field),
+        )),*]
+    };
+}
+
+const RUST_MEMBERS: &[(&str, usize, usize)] = &members![
+    time,
+    delta_time,
+    frame,
+    scale_factor,
+    resolution,
+    logical_resolution,
+    pointer,
+];
+
+const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+// Check that the uniform buffer is laid out the way that the shaders expect.
+const _: () = {
+    assert!(
+        size_of::<Globals>() == WGSL_SIZE,
+        "`Globals` has a different size in globals.wgsl"
+    );
+    assert!(
+        RUST_MEMBERS.len() == WGSL_MEMBERS.len(),
+        "`Globals` has a different number of members in globals.wgsl"
+    );
+    let mut i = 0;
+    while i < RUST_MEMBERS.len() {
+        let (name, offset, size) = RUST_MEMBERS[i];
+        let (wgsl_name, wgsl_offset, wgsl_size) = WGSL_MEMBERS[i];
+        assert!(
+            str_eq(name, wgsl_name),
+            "the members of `Globals` are named or ordered differently in globals.wgsl"
+        );
+        assert!(
+            offset == wgsl_offset && size == wgsl_size,
+            "a member of `Globals` has a different offset or size in globals.wgsl"
+        );
+        i += 1;
+    }
+};
@@ -0,0 +1,22 @@
+// Prepended to every shader created with `GpuContext::create_shader_module`.
+//
+// Must match `Globals` in `globals.rs`, which is checked when building.
+struct Globals {
+    // Seconds since the renderer was created.
+    time: f32,
+    // Seconds since the previous frame, zero for the first frame.
+    delta_time: f32,
+    // The number of frames presented before this one.
+    frame: u32,
+    scale_factor: f32,
+    // The size of the target, in physical pixels.
+    resolution: vec2<f32>,
+    // The size of the target, in logical points.
+    logical_resolution: vec2<f32>,
+    // The position of the pointer in physical pixels from the top left, or
+    // negative if the pointer is not over the view.
+    pointer: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> globals: Globals;
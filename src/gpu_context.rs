@@ -0,0 +1,512 @@
+//! GPU state that is shared between all renderers.
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::globals::{self, Globals};
+use crate::pipeline_cache::PipelineCacheFile;
+use crate::shader_watcher::ShaderWatcher;
+
+/// The device and queue used by every [`Renderer`], as well as the pipeline
+/// cache and the hot-reloaded shader that the scenes create their pipelines
+/// with, and the resources that the scenes share between the renderers.
+///
+/// This is created once, and then shared between all the views, which then
+/// only keep their per-surface state themselves.
+///
+/// If the device is lost, a replacement context can be created with
+/// [`GpuContext::recreate`].
+///
+/// [`Renderer`]: crate::renderer::Renderer
+#[derive(Debug)]
+pub struct GpuContext {
+    instance: wgpu::Instance,
+    adapter: wgpu::Adapter,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    globals_bind_group_layout: wgpu::BindGroupLayout,
+    // `None` if pipeline caching is not supported.
+    pipeline_cache: Option<Mutex<PipelineCacheFile>>,
+    shader_watcher: Option<ShaderWatcher>,
+    // The last shader that was reloaded, and how many times it was, see
+    // `reloaded_shader`.
+    reloaded_shader: Mutex<Option<wgpu::ShaderModule>>,
+    shader_generation: AtomicU64,
+    // See `shared`.
+    shared: Mutex<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>,
+    // Set from the device lost callback.
+    lost: Arc<AtomicBool>,
+    // Called after `lost` is set, see `set_device_lost_callback`.
+    device_lost_callback: Arc<Mutex<Option<DeviceLostCallback>>>,
+    force_fallback_adapter: bool,
+    // Shared between all the renderers that notice that the device was lost,
+    // and with the thread that requests the replacement.
+    replacement: Arc<Mutex<Replacement>>,
+    recreated_callback: Option<RecreatedCallback>,
+}
+
+/// How far requesting a replacement context has come, after the device was
+/// lost.
+#[derive(Debug, Default)]
+enum Replacement {
+    #[default]
+    NotRequested,
+    /// Being requested on a background thread.
+    Pending,
+    Ready(Arc<GpuContext>),
+    /// The last request failed, and is retried once `retry_at` has passed.
+    Failed {
+        retry_at: Instant,
+        backoff: Duration,
+    },
+}
+
+/// How long to wait before retrying after the first failed request for a
+/// replacement context. Doubled after every further failure.
+const INITIAL_RECREATE_BACKOFF: Duration = Duration::from_millis(500);
+
+/// The longest time to wait between requests for a replacement context.
+const MAX_RECREATE_BACKOFF: Duration = Duration::from_secs(30);
+
+/// See [`GpuContext::set_device_lost_callback`].
+struct DeviceLostCallback(Box<dyn FnOnce(wgpu::DeviceLostReason, String) + Send>);
+
+impl fmt::Debug for DeviceLostCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeviceLostCallback").finish_non_exhaustive()
+    }
+}
+
+/// See [`GpuContext::set_recreated_callback`].
+#[derive(Clone)]
+struct RecreatedCallback(Arc<dyn Fn() + Send + Sync>);
+
+impl fmt::Debug for RecreatedCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecreatedCallback").finish_non_exhaustive()
+    }
+}
+
+impl GpuContext {
+    /// Create a context using the default adapter.
+    pub async fn new() -> Self {
+        Self::try_new().await.expect("Failed to create GPU context")
+    }
+
+    /// Create a context using the default adapter, or return why no adapter
+    /// or device could be requested.
+    pub async fn try_new() -> Result<Self, Box<dyn Error>> {
+        Self::request(wgpu::Instance::default(), false).await
+    }
+
+    /// Create a context using the fallback (software) adapter, which works
+    /// without a window or a GPU.
+    pub async fn new_headless() -> Self {
+        Self::request(wgpu::Instance::default(), true)
+            .await
+            .expect("Failed to create GPU context")
+    }
+
+    async fn request(
+        instance: wgpu::Instance,
+        force_fallback_adapter: bool,
+    ) -> Result<Self, Box<dyn Error>> {
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                force_fallback_adapter,
+                // Surfaces are created later, once we have a view, so we
+                // cannot request an adapter which is compatible with them.
+                compatible_surface: None,
+            })
+            .await?;
+
+        // Only supported on some backends, in which case we use them to avoid
+        // recompiling the pipelines on every launch, and to measure how long
+        // the GPU spends on each frame.
+        let required_features =
+            adapter.features() & (wgpu::Features::PIPELINE_CACHE | wgpu::Features::TIMESTAMP_QUERY);
+
+        // Create the logical device and command queue
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                label: None,
+                required_features,
+                // Make sure we use the texture resolution limits from the adapter, so we can support images the size of the swapchain.
+                required_limits: wgpu::Limits::downlevel_webgl2_defaults()
+                    .using_resolution(adapter.limits()),
+                experimental_features: wgpu::ExperimentalFeatures::disabled(),
+                memory_hints: wgpu::MemoryHints::Performance,
+                trace: wgpu::Trace::default(),
+            })
+            .await?;
+
+        let mut this = Self::from_device(instance, adapter, device, queue);
+        this.force_fallback_adapter = force_fallback_adapter;
+        Ok(this)
+    }
+
+    /// Create a context from an existing device, for example one that is
+    /// shared with other parts of a larger engine.
+    ///
+    /// This replaces any device lost callback that was set on `device`, use
+    /// [`GpuContext::set_device_lost_callback`] instead.
+    pub fn from_device(
+        instance: wgpu::Instance,
+        adapter: wgpu::Adapter,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+    ) -> Self {
+        let lost = Arc::new(AtomicBool::new(false));
+        let lost_clone = Arc::clone(&lost);
+        let device_lost_callback = Arc::new(Mutex::new(None::<DeviceLostCallback>));
+        let device_lost_callback_clone = Arc::clone(&device_lost_callback);
+        device.set_device_lost_callback(move |reason, message| {
+            tracing::error!(?reason, message, "device lost");
+            lost_clone.store(true, Ordering::Relaxed);
+            if let Some(callback) = device_lost_callback_clone.lock().unwrap().take() {
+                (callback.0)(reason, message);
+            }
+        });
+
+        let pipeline_cache = PipelineCacheFile::load(&device, &adapter).map(Mutex::new);
+
+        let globals_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Globals"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(size_of::<Globals>() as u64),
+                    },
+                    count: None,
+                }],
+            });
+
+        Self {
+            instance,
+            adapter,
+            device,
+            queue,
+            globals_bind_group_layout,
+            pipeline_cache,
+            shader_watcher: None,
+            reloaded_shader: Mutex::new(None),
+            shader_generation: AtomicU64::new(0),
+            shared: Mutex::default(),
+            lost,
+            device_lost_callback,
+            force_fallback_adapter: false,
+            replacement: Arc::default(),
+            recreated_callback: None,
+        }
+    }
+
+    /// Call `callback` when the device is lost, after [`GpuContext::is_lost`]
+    /// starts returning `true`.
+    ///
+    /// This is not carried over to the replacement context.
+    pub fn set_device_lost_callback(
+        &self,
+        callback: impl FnOnce(wgpu::DeviceLostReason, String) + Send + 'static,
+    ) {
+        *self.device_lost_callback.lock().unwrap() = Some(DeviceLostCallback(Box::new(callback)));
+    }
+
+    /// Call `callback` from a background thread once a replacement context
+    /// has been created, so that the renderers can be redrawn to pick it up.
+    ///
+    /// This is carried over to the replacement context.
+    pub fn set_recreated_callback(&mut self, callback: impl Fn() + Send + Sync + 'static) {
+        self.recreated_callback = Some(RecreatedCallback(Arc::new(callback)));
+    }
+
+    /// Whether the device has been lost, and the context must be recreated.
+    pub fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::Relaxed)
+    }
+
+    /// Get a new context to replace this one after the device was lost.
+    ///
+    /// The replacement is requested on a background thread, so that the
+    /// caller isn't blocked while requesting the adapter and device. It is
+    /// only created once, and then shared between every caller, so that the
+    /// views keep sharing a single device. It uses the same instance, so that
+    /// existing surfaces can be reconfigured with the new device.
+    ///
+    /// Returns `None` until the replacement is ready, in which case this
+    /// should be called again later, for example when the callback set with
+    /// [`GpuContext::set_recreated_callback`] is called. If the request
+    /// fails, it is retried on a later call, with an increasing delay between
+    /// attempts.
+    pub fn recreate(&self) -> Option<Arc<Self>> {
+        let mut replacement = self.replacement.lock().unwrap();
+        let backoff = match &*replacement {
+            Replacement::Ready(gpu) => return Some(Arc::clone(gpu)),
+            Replacement::Pending => return None,
+            Replacement::Failed { retry_at, .. } if Instant::now() < *retry_at => return None,
+            Replacement::Failed { backoff, .. } => (*backoff * 2).min(MAX_RECREATE_BACKOFF),
+            Replacement::NotRequested => INITIAL_RECREATE_BACKOFF,
+        };
+        *replacement = Replacement::Pending;
+        drop(replacement);
+
+        let replacement = Arc::clone(&self.replacement);
+        let instance = self.instance.clone();
+        let force_fallback_adapter = self.force_fallback_adapter;
+        let shader_path = self
+            .shader_watcher
+            .as_ref()
+            .map(|watcher| watcher.path().clone());
+        let recreated_callback = self.recreated_callback.clone();
+        thread::spawn(move || {
+            let start = Instant::now();
+            let request = Self::request(instance, force_fallback_adapter);
+            match pollster::block_on(request) {
+                Ok(mut gpu) => {
+                    if let Some(shader_path) = shader_path {
+                        gpu.enable_shader_hot_reload(shader_path);
+                    }
+                    gpu.recreated_callback = recreated_callback.clone();
+                    tracing::info!(
+                        adapter = ?gpu.adapter.get_info(),
+                        elapsed = ?start.elapsed(),
+                        "recreated GPU context"
+                    );
+                    *replacement.lock().unwrap() = Replacement::Ready(Arc::new(gpu));
+                    if let Some(callback) = recreated_callback {
+                        (callback.0)();
+                    }
+                }
+                Err(err) => {
+                    tracing::error!(%err, ?backoff, "failed recreating GPU context, will retry");
+                    *replacement.lock().unwrap() = Replacement::Failed {
+                        retry_at: Instant::now() + backoff,
+                        backoff,
+                    };
+                }
+            }
+        });
+        None
+    }
+
+    pub fn instance(&self) -> &wgpu::Instance {
+        &self.instance
+    }
+
+    pub fn adapter(&self) -> &wgpu::Adapter {
+        &self.adapter
+    }
+
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    /// The layout of the [`Globals`] bind group, which the renderer binds to
+    /// group 0 before a scene renders, and which must thus be the first
+    /// layout of every scene's pipelines.
+    pub fn globals_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.globals_bind_group_layout
+    }
+
+    /// Compile a WGSL shader, with the `Globals` struct and the `globals`
+    /// uniform declared before `source`.
+    ///
+    /// The line numbers in validation errors include these declarations, so
+    /// they are [`shader_prelude_lines`] past the lines of `source`, which
+    /// `label` can help find.
+    pub fn create_shader_module(&self, label: &str, source: &str) -> wgpu::ShaderModule {
+        create_shader_module(&self.device, label, source)
+    }
+
+    /// The highest sample count that is at most `requested`, and which is
+    /// supported for rendering into textures of all the given formats, e.g.
+    /// the color and the depth format.
+    pub fn supported_sample_count(&self, formats: &[wgpu::TextureFormat], requested: u32) -> u32 {
+        let sample_count = [16, 8, 4, 2]
+            .into_iter()
+            .find(|&count| count <= requested && self.supports_sample_count(formats, count))
+            .unwrap_or(1);
+        if sample_count != requested {
+            tracing::warn!(
+                ?formats,
+                requested,
+                sample_count,
+                "sample count not supported by the adapter, falling back"
+            );
+        }
+        sample_count
+    }
+
+    /// Whether textures of all the given formats can be rendered into with
+    /// `sample_count` samples per pixel.
+    pub fn supports_sample_count(
+        &self,
+        formats: &[wgpu::TextureFormat],
+        sample_count: u32,
+    ) -> bool {
+        formats.iter().all(|&format| {
+            let flags = self.adapter.get_texture_format_features(format).flags;
+            flags.sample_count_supported(sample_count)
+        })
+    }
+
+    /// Get the resource of type `T` that is shared between every scene using
+    /// this context, creating it with `create` the first time.
+    ///
+    /// This is for resources that only depend on the device, such as shader
+    /// modules and pipelines, so that every view doesn't create its own. A
+    /// replacement context starts without any, see [`GpuContext::recreate`].
+    pub fn shared<T: Any + Send + Sync>(&self, create: impl FnOnce() -> T) -> Arc<T> {
+        let downcast = |resource: &Arc<dyn Any + Send + Sync>| {
+            Arc::clone(resource)
+                .downcast::<T>()
+                .expect("shared resources are keyed by their type")
+        };
+        if let Some(resource) = self.shared.lock().unwrap().get(&TypeId::of::<T>()) {
+            return downcast(resource);
+        }
+        // Created without holding the lock, so that `create` may use other
+        // shared resources. If another thread got there first, its resource
+        // is used instead.
+        let resource: Arc<dyn Any + Send + Sync> = Arc::new(create());
+        let mut shared = self.shared.lock().unwrap();
+        downcast(shared.entry(TypeId::of::<T>()).or_insert(resource))
+    }
+
+    /// Create a render pipeline using the pipeline cache, if supported,
+    /// instead of the cache in `descriptor`.
+    ///
+    /// `key` identifies the pipeline across launches, to log how much time
+    /// the cache saved, and must not contain tabs or newlines.
+    pub fn create_render_pipeline(
+        &self,
+        key: &str,
+        descriptor: &wgpu::RenderPipelineDescriptor<'_>,
+    ) -> wgpu::RenderPipeline {
+        let Some(pipeline_cache) = &self.pipeline_cache else {
+            return self.device.create_render_pipeline(descriptor);
+        };
+        let mut pipeline_cache = pipeline_cache.lock().unwrap();
+        let start = Instant::now();
+        let render_pipeline = self
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                cache: Some(pipeline_cache.cache()),
+                ..descriptor.clone()
+            });
+        pipeline_cache.pipeline_created(key, start.elapsed());
+        render_pipeline
+    }
+
+    /// Call `f`, and return the first validation error that it caused
+    /// instead of reporting it as an uncaptured error, which panics by
+    /// default.
+    ///
+    /// Useful for creating pipelines from a reloaded shader, which may no
+    /// longer match the rest of the scene.
+    pub fn catch_validation_error<T>(&self, f: impl FnOnce() -> T) -> Result<T, wgpu::Error> {
+        let error_scope = self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let value = f();
+        match pollster::block_on(error_scope.pop()) {
+            None => Ok(value),
+            Some(err) => Err(err),
+        }
+    }
+
+    /// Watch the shader source at `path`, and recompile it whenever it
+    /// changes, for the scenes to pick up with
+    /// [`GpuContext::reloaded_shader`].
+    ///
+    /// This is only supported in debug builds, release builds always use
+    /// the shaders embedded in the binary.
+    pub fn enable_shader_hot_reload(&mut self, path: impl Into<PathBuf>) {
+        let path = path.into();
+        if !cfg!(debug_assertions) {
+            tracing::warn!("shader hot-reload is only available in debug builds");
+            return;
+        }
+        tracing::info!(path = %path.display(), "watching shader for changes");
+        // Start from an empty source, so that the file is loaded once, even
+        // if it was edited before launching, or before the context was
+        // recreated.
+        self.shader_watcher = Some(ShaderWatcher::new(path, String::new()));
+    }
+
+    /// Recompile the shader if its source has changed on disk.
+    ///
+    /// If the new shader fails validation, the old one is kept.
+    pub(crate) fn reload_shader_if_changed(&self) {
+        let Some(shader_watcher) = &self.shader_watcher else {
+            return;
+        };
+        let Some(source) = shader_watcher.take_changed_source() else {
+            return;
+        };
+
+        let path = shader_watcher.path().display().to_string();
+        match self.catch_validation_error(|| create_shader_module(&self.device, &path, &source)) {
+            Ok(shader) => {
+                tracing::info!(path, "reloaded shader");
+                *self.reloaded_shader.lock().unwrap() = Some(shader);
+                self.shader_generation.fetch_add(1, Ordering::Release);
+            }
+            Err(err) => {
+                tracing::error!(
+                    path,
+                    "failed reloading shader, keeping the old one, the line numbers are {} past \
+                     the lines in the file:\n{err}",
+                    shader_prelude_lines()
+                );
+            }
+        }
+    }
+
+    /// The shader that was last reloaded from the file passed to
+    /// [`GpuContext::enable_shader_hot_reload`], if it has been reloaded
+    /// since `generation` was updated.
+    ///
+    /// Each scene keeps its own `generation`, starting at zero, so that
+    /// every scene picks up every reload. This is cheap to call every frame.
+    /// The pipelines must be created from the shader with
+    /// [`GpuContext::catch_validation_error`], and the old ones kept if that
+    /// fails.
+    pub fn reloaded_shader(&self, generation: &mut u64) -> Option<wgpu::ShaderModule> {
+        let current = self.shader_generation.load(Ordering::Acquire);
+        if current == *generation {
+            return None;
+        }
+        *generation = current;
+        self.reloaded_shader.lock().unwrap().clone()
+    }
+}
+
+/// The number of lines that [`GpuContext::create_shader_module`] declares
+/// before the source of the shader.
+pub fn shader_prelude_lines() -> usize {
+    shader_prelude().lines().count()
+}
+
+fn shader_prelude() -> String {
+    format!("{}\n", globals::WGSL)
+}
+
+fn create_shader_module(device: &wgpu::Device, label: &str, source: &str) -> wgpu::ShaderModule {
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(format!("{}{source}", shader_prelude()).into()),
+    })
+}
@@ -0,0 +1,103 @@
+//! Measuring how long the GPU spends on each frame.
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The size of the two timestamps written by each frame, in bytes.
+const TIMESTAMPS_SIZE: u64 = 2 * std::mem::size_of::<u64>() as u64;
+
+/// Writes timestamps at the beginning and end of a render pass, and reads
+/// them back without blocking.
+///
+/// Only one frame is measured at a time: frames that are rendered while the
+/// previous measurement is still being read back are not measured.
+#[derive(Debug)]
+pub struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buf: wgpu::Buffer,
+    readback_buf: wgpu::Buffer,
+    // Set while `readback_buf` is mapped, or waiting to be.
+    pending: Arc<AtomicBool>,
+    // Nanoseconds per timestamp tick.
+    period: f32,
+}
+
+impl GpuTimer {
+    /// Returns `None` if the device does not support timestamp queries.
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            tracing::debug!("timestamp queries are not supported, not measuring GPU time");
+            return None;
+        }
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("Timestamps"),
+            ty: wgpu::QueryType::Timestamp,
+            count: 2,
+        });
+        let resolve_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Timestamp Resolve Buffer"),
+            size: TIMESTAMPS_SIZE,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Timestamp Readback Buffer"),
+            size: TIMESTAMPS_SIZE,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        Some(Self {
+            query_set,
+            resolve_buf,
+            readback_buf,
+            pending: Arc::new(AtomicBool::new(false)),
+            period: queue.get_timestamp_period(),
+        })
+    }
+
+    /// Whether the next frame can be measured.
+    pub fn is_ready(&self) -> bool {
+        !self.pending.load(Ordering::Acquire)
+    }
+
+    /// The timestamps to write in the render pass.
+    pub fn timestamp_writes(&self) -> wgpu::RenderPassTimestampWrites<'_> {
+        wgpu::RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(0),
+            end_of_pass_write_index: Some(1),
+        }
+    }
+
+    /// Copy the timestamps into the readback buffer, after the render pass.
+    pub fn resolve(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.resolve_query_set(&self.query_set, 0..2, &self.resolve_buf, 0);
+        encoder.copy_buffer_to_buffer(&self.resolve_buf, 0, &self.readback_buf, 0, TIMESTAMPS_SIZE);
+    }
+
+    /// Read back the timestamps once the submission has finished, and call
+    /// `on_gpu_time` with the time between them.
+    pub fn read_back(&self, on_gpu_time: impl FnOnce(Duration) + Send + 'static) {
+        self.pending.store(true, Ordering::Release);
+        let readback_buf = self.readback_buf.clone();
+        let pending = Arc::clone(&self.pending);
+        let period = self.period;
+        self.readback_buf
+            .map_async(wgpu::MapMode::Read, .., move |result| {
+                if let Err(err) = result {
+                    tracing::warn!(%err, "failed reading back timestamps");
+                    pending.store(false, Ordering::Release);
+                    return;
+                }
+                let timestamps: [u64; 2] = {
+                    let data = readback_buf.get_mapped_range(..);
+                    bytemuck::pod_read_unaligned(&data)
+                };
+                readback_buf.unmap();
+                pending.store(false, Ordering::Release);
+
+                let ticks = timestamps[1].saturating_sub(timestamps[0]);
+                on_gpu_time(Duration::from_nanos((ticks as f64 * period as f64) as u64));
+            });
+    }
+}
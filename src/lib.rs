@@ -0,0 +1,30 @@
+#![deny(unsafe_op_in_unsafe_fn)]
+pub mod alpha_mode;
+#[cfg(target_os = "macos")]
+pub mod appkit_main;
+pub mod config;
+pub mod frame_counter;
+pub mod globals;
+pub mod gpu_context;
+pub mod gpu_timer;
+mod math;
+pub mod mesh;
+pub mod model;
+pub mod model_scene;
+pub mod pipeline_cache;
+pub mod present_mode;
+pub mod render_target;
+pub mod renderer;
+#[cfg(target_vendor = "apple")]
+mod run_loop;
+pub mod scene;
+mod shader_watcher;
+pub mod span_timing;
+pub mod stats_sink;
+pub mod surface_format;
+pub mod trace_recorder;
+#[cfg(all(target_vendor = "apple", not(target_os = "macos")))]
+pub mod uikit_main;
+#[cfg(target_vendor = "apple")]
+mod view;
+pub mod wgpu_triangle;
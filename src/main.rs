@@ -1,29 +1,57 @@
 #![deny(unsafe_op_in_unsafe_fn)]
-use objc2::MainThreadMarker;
 use tracing_subscriber::filter::EnvFilter;
-
-#[cfg(target_os = "macos")]
-mod appkit_main;
-mod frame_counter;
-mod run_loop;
-#[cfg(not(target_os = "macos"))]
-mod uikit_main;
-mod view;
-mod wgpu_triangle;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use wgpu_objc2_example::config::{Config, ConfigError};
+use wgpu_objc2_example::span_timing::SpanTimingLayer;
+use wgpu_objc2_example::trace_recorder;
 
 fn main() {
-    tracing_subscriber::fmt()
-        .with_env_filter(
+    // The spans around each stage of a frame are at the debug level, so the
+    // span timings are only summarized with e.g.
+    // `RUST_LOG=wgpu_objc2_example=debug`.
+    tracing_subscriber::registry()
+        .with(
             EnvFilter::builder()
                 .with_default_directive("wgpu_objc2_example=info".parse().unwrap())
                 .from_env_lossy(),
         )
+        .with(tracing_subscriber::fmt::layer())
+        .with(SpanTimingLayer::new())
         .init();
 
-    let mtm = MainThreadMarker::new().unwrap();
+    let config = match Config::from_env() {
+        Ok(config) => config,
+        Err(ConfigError::Help) => {
+            print!("{}", Config::usage());
+            return;
+        }
+        Err(err) => {
+            eprint!("error: {err}\n\n{}", Config::usage());
+            std::process::exit(2);
+        }
+    };
+    tracing::info!(?config, mtkview = cfg!(feature = "mtkview"), "starting");
+
+    if let Some(path) = config.trace_path() {
+        if let Err(err) = trace_recorder::start(path) {
+            tracing::error!(%err, path, "failed starting trace recording");
+        }
+    }
+
+    #[cfg(target_vendor = "apple")]
+    {
+        let mtm = objc2::MainThreadMarker::new().unwrap();
+
+        #[cfg(target_os = "macos")]
+        wgpu_objc2_example::appkit_main::main(mtm, config);
+        #[cfg(not(target_os = "macos"))]
+        wgpu_objc2_example::uikit_main::main(mtm, config);
+    }
 
-    #[cfg(target_os = "macos")]
-    appkit_main::main(mtm);
-    #[cfg(not(target_os = "macos"))]
-    uikit_main::main(mtm);
+    #[cfg(not(target_vendor = "apple"))]
+    {
+        tracing::error!("the windowed example is only supported on Apple platforms");
+        std::process::exit(1);
+    }
 }
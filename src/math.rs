@@ -0,0 +1,89 @@
+//! The little linear algebra needed for placing models.
+//!
+//! Matrices are column-major, like in WGSL and glTF.
+
+pub type Vec3 = [f32; 3];
+pub type Mat4 = [[f32; 4]; 4];
+
+pub const IDENTITY: Mat4 = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+pub fn sub(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub fn dot(a: Vec3, b: Vec3) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub fn cross(a: Vec3, b: Vec3) -> Vec3 {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+/// `v` scaled to unit length, or `None` if it has no length.
+pub fn normalize(v: Vec3) -> Option<Vec3> {
+    let length = dot(v, v).sqrt();
+    (length > f32::EPSILON).then(|| v.map(|c| c / length))
+}
+
+pub fn mul(a: &Mat4, b: &Mat4) -> Mat4 {
+    let mut out = [[0.0; 4]; 4];
+    for (col, out_col) in out.iter_mut().enumerate() {
+        for (row, out) in out_col.iter_mut().enumerate() {
+            *out = (0..4).map(|k| a[k][row] * b[col][k]).sum();
+        }
+    }
+    out
+}
+
+pub fn transform_point(m: &Mat4, p: Vec3) -> Vec3 {
+    let row = |i: usize| m[0][i] * p[0] + m[1][i] * p[1] + m[2][i] * p[2] + m[3][i];
+    let w = row(3);
+    [row(0) / w, row(1) / w, row(2) / w]
+}
+
+/// Transform a normal by the inverse transpose of the upper 3x3 of `m`, so
+/// that it stays perpendicular to the surface under non-uniform scaling.
+pub fn transform_normal(m: &Mat4, n: Vec3) -> Vec3 {
+    let [x, y, z] = [0, 1, 2].map(|col| [m[col][0], m[col][1], m[col][2]]);
+    // The columns of the cofactor matrix, which is the inverse transpose
+    // scaled by the determinant.
+    let cofactor = [cross(y, z), cross(z, x), cross(x, y)];
+    let sign = dot(x, cofactor[0]).signum();
+    let n = [0, 1, 2].map(|i| sign * dot([cofactor[0][i], cofactor[1][i], cofactor[2][i]], n));
+    normalize(n).unwrap_or(n)
+}
+
+/// A right-handed view matrix, looking from `eye` at `target`.
+pub fn look_at(eye: Vec3, target: Vec3, up: Vec3) -> Mat4 {
+    let f = normalize(sub(target, eye)).unwrap_or([0.0, 0.0, -1.0]);
+    let s = normalize(cross(f, up)).unwrap_or([1.0, 0.0, 0.0]);
+    let u = cross(s, f);
+    [
+        [s[0], u[0], -f[0], 0.0],
+        [s[1], u[1], -f[1], 0.0],
+        [s[2], u[2], -f[2], 0.0],
+        [-dot(s, eye), -dot(u, eye), dot(f, eye), 1.0],
+    ]
+}
+
+/// A right-handed perspective projection, with depth from 0 at `near` to 1
+/// at `far`, as used by `wgpu`.
+pub fn perspective(fov_y: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+    let f = 1.0 / (fov_y / 2.0).tan();
+    let range = far / (near - far);
+    [
+        [f / aspect, 0.0, 0.0, 0.0],
+        [0.0, f, 0.0, 0.0],
+        [0.0, 0.0, range, -1.0],
+        [0.0, 0.0, near * range, 0.0],
+    ]
+}
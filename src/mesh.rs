@@ -0,0 +1,331 @@
+//! Drawing arbitrary geometry from vertex and index buffers.
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::gpu_context::GpuContext;
+use crate::scene::{Scene, TargetFormat};
+
+/// The smallest size of the vertex and index buffers, in bytes.
+const MIN_BUFFER_SIZE: wgpu::BufferAddress = 1024;
+
+/// A vertex of a [`Mesh`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Vertex {
+    /// The position in normalized device coordinates.
+    pub position: [f32; 3],
+    /// The linear RGBA color, which is interpolated between the vertices.
+    pub color: [f32; 4],
+    /// The texture coordinates.
+    pub uv: [f32; 2],
+}
+
+impl Vertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x4, 2 => Float32x2];
+
+    /// The layout of a vertex buffer containing `Vertex`es, matching the
+    /// `VertexInput` of `mesh.wgsl`.
+    pub const LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: size_of::<Vertex>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &Self::ATTRIBUTES,
+    };
+
+    pub fn new(position: [f32; 3], color: [f32; 4], uv: [f32; 2]) -> Self {
+        Self {
+            position,
+            color,
+            uv,
+        }
+    }
+}
+
+/// Indexed triangles, which can be changed while they are being drawn.
+///
+/// Clones share the same geometry, so that the host can keep changing it
+/// after passing it to a [`MeshScene`]. Changes are uploaded before the next
+/// frame is rendered, and only the appended part is uploaded when appending.
+#[derive(Debug, Clone, Default)]
+pub struct Mesh {
+    data: Arc<Mutex<MeshData>>,
+}
+
+#[derive(Debug, Default)]
+struct MeshData {
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+    // Incremented whenever the geometry is replaced, rather than appended to.
+    generation: u64,
+}
+
+impl Mesh {
+    /// An empty mesh.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A mesh with the given geometry, see [`Mesh::replace`].
+    pub fn from_geometry(vertices: &[Vertex], indices: &[u32]) -> Self {
+        let this = Self::new();
+        this.replace(vertices, indices);
+        this
+    }
+
+    /// Replace the geometry with the triangles given by `indices` into
+    /// `vertices`.
+    ///
+    /// # Panics
+    ///
+    /// If an index is out of bounds of `vertices`.
+    pub fn replace(&self, vertices: &[Vertex], indices: &[u32]) {
+        check_indices(vertices, indices);
+        let mut data = self.data.lock().unwrap();
+        data.vertices = vertices.to_vec();
+        data.indices = indices.to_vec();
+        data.generation += 1;
+    }
+
+    /// Add the triangles given by `indices` into `vertices` to the existing
+    /// geometry.
+    ///
+    /// The indices are relative to the start of `vertices`.
+    ///
+    /// # Panics
+    ///
+    /// If an index is out of bounds of `vertices`, or if the mesh would have
+    /// more vertices than fit in a `u32` index.
+    pub fn append(&self, vertices: &[Vertex], indices: &[u32]) {
+        check_indices(vertices, indices);
+        let mut data = self.data.lock().unwrap();
+        let base = u32::try_from(data.vertices.len()).expect("too many vertices");
+        // Offset before changing anything, so that the mesh is left as it was
+        // if there are too many vertices.
+        let indices: Vec<u32> = indices
+            .iter()
+            .map(|index| base.checked_add(*index).expect("too many vertices"))
+            .collect();
+        data.vertices.extend_from_slice(vertices);
+        data.indices.extend(indices);
+    }
+
+    /// Remove all the geometry.
+    pub fn clear(&self) {
+        self.replace(&[], &[]);
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.data.lock().unwrap().vertices.len()
+    }
+
+    pub fn index_count(&self) -> usize {
+        self.data.lock().unwrap().indices.len()
+    }
+}
+
+fn check_indices(vertices: &[Vertex], indices: &[u32]) {
+    if let Some(index) = indices
+        .iter()
+        .find(|&&index| index as usize >= vertices.len())
+    {
+        panic!(
+            "index {index} is out of bounds of {} vertices",
+            vertices.len()
+        );
+    }
+}
+
+/// Draws a [`Mesh`] as a list of triangles.
+#[derive(Debug)]
+pub struct MeshScene {
+    mesh: Mesh,
+    // `None` until initialized.
+    resources: Option<Resources>,
+}
+
+/// The resources that must be recreated along with the device.
+#[derive(Debug)]
+struct Resources {
+    render_pipeline: wgpu::RenderPipeline,
+    vertex_buf: GrowableBuffer,
+    index_buf: GrowableBuffer,
+    // `None` if nothing was uploaded yet.
+    uploaded: Option<Uploaded>,
+}
+
+/// How much of which generation of the mesh is in the buffers.
+#[derive(Debug, Clone, Copy)]
+struct Uploaded {
+    generation: u64,
+    vertex_count: usize,
+    index_count: usize,
+}
+
+impl MeshScene {
+    pub fn new(mesh: Mesh) -> Self {
+        Self {
+            mesh,
+            resources: None,
+        }
+    }
+
+    /// The mesh that is drawn, which can be changed at any time.
+    pub fn mesh(&self) -> &Mesh {
+        &self.mesh
+    }
+}
+
+impl Scene for MeshScene {
+    fn init(&mut self, gpu: &GpuContext, format: TargetFormat) {
+        let device = gpu.device();
+        let shader = gpu.create_shader_module("Mesh Shader", include_str!("mesh.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[gpu.globals_bind_group_layout()],
+            immediate_size: 0,
+        });
+        let render_pipeline = gpu.create_render_pipeline(
+            &format!("Mesh {format:?}"),
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("Mesh Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[Vertex::LAYOUT],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some("fs_main"),
+                    compilation_options: Default::default(),
+                    targets: &[Some(format.format.into())],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: format.depth_stencil(),
+                multisample: format.multisample(),
+                multiview_mask: None,
+                cache: None,
+            },
+        );
+
+        let mut resources = Resources {
+            render_pipeline,
+            vertex_buf: GrowableBuffer::new(device, "Vertex Buffer", wgpu::BufferUsages::VERTEX),
+            index_buf: GrowableBuffer::new(device, "Index Buffer", wgpu::BufferUsages::INDEX),
+            uploaded: None,
+        };
+        resources.upload(gpu, &self.mesh);
+        self.resources = Some(resources);
+    }
+
+    fn resize(&mut self, _gpu: &GpuContext, _width: u32, _height: u32, _scale_factor: f32) {}
+
+    fn update(&mut self, gpu: &GpuContext, _dt: Duration) {
+        if let Some(resources) = &mut self.resources {
+            resources.upload(gpu, &self.mesh);
+        }
+    }
+
+    fn render(&mut self, rpass: &mut wgpu::RenderPass<'_>) {
+        let Some(resources) = &self.resources else {
+            return;
+        };
+        let index_count = resources
+            .uploaded
+            .map_or(0, |uploaded| uploaded.index_count);
+        if index_count == 0 {
+            return;
+        }
+        rpass.set_pipeline(&resources.render_pipeline);
+        rpass.set_vertex_buffer(0, resources.vertex_buf.buffer.slice(..));
+        rpass.set_index_buffer(
+            resources.index_buf.buffer.slice(..),
+            wgpu::IndexFormat::Uint32,
+        );
+        rpass.draw_indexed(0..index_count as u32, 0, 0..1);
+    }
+}
+
+impl Resources {
+    /// Upload the parts of the mesh that changed since the last upload.
+    fn upload(&mut self, gpu: &GpuContext, mesh: &Mesh) {
+        let data = mesh.data.lock().unwrap();
+        let (vertices_from, indices_from) = match self.uploaded {
+            Some(uploaded) if uploaded.generation == data.generation => {
+                (uploaded.vertex_count, uploaded.index_count)
+            }
+            _ => (0, 0),
+        };
+        self.vertex_buf.write(
+            gpu,
+            bytemuck::cast_slice(&data.vertices),
+            vertices_from * size_of::<Vertex>(),
+        );
+        self.index_buf.write(
+            gpu,
+            bytemuck::cast_slice(&data.indices),
+            indices_from * size_of::<u32>(),
+        );
+        self.uploaded = Some(Uploaded {
+            generation: data.generation,
+            vertex_count: data.vertices.len(),
+            index_count: data.indices.len(),
+        });
+    }
+}
+
+/// A buffer that is recreated with a larger size when its contents no longer
+/// fit.
+#[derive(Debug)]
+struct GrowableBuffer {
+    label: &'static str,
+    usage: wgpu::BufferUsages,
+    buffer: wgpu::Buffer,
+}
+
+impl GrowableBuffer {
+    fn new(device: &wgpu::Device, label: &'static str, usage: wgpu::BufferUsages) -> Self {
+        Self {
+            label,
+            usage,
+            buffer: create_buffer(device, label, usage, MIN_BUFFER_SIZE),
+        }
+    }
+
+    /// Update the buffer to contain `contents`, where only the bytes from
+    /// `changed_from` onwards differ from what was written before.
+    fn write(&mut self, gpu: &GpuContext, contents: &[u8], changed_from: usize) {
+        let size = contents.len() as wgpu::BufferAddress;
+        let changed_from = if size > self.buffer.size() {
+            // Double the size (at least), to avoid regrowing on every append.
+            let new_size = size.next_power_of_two().max(MIN_BUFFER_SIZE);
+            tracing::debug!(label = self.label, size = new_size, "growing buffer");
+            self.buffer = create_buffer(gpu.device(), self.label, self.usage, new_size);
+            0
+        } else {
+            changed_from
+        };
+        if changed_from < contents.len() {
+            gpu.queue().write_buffer(
+                &self.buffer,
+                changed_from as wgpu::BufferAddress,
+                &contents[changed_from..],
+            );
+        }
+    }
+}
+
+fn create_buffer(
+    device: &wgpu::Device,
+    label: &str,
+    usage: wgpu::BufferUsages,
+    size: wgpu::BufferAddress,
+) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size,
+        usage: usage | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
@@ -0,0 +1,452 @@
+//! Loading static meshes from glTF 2.0 and Wavefront OBJ files.
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::math::{self, Mat4, Vec3};
+
+/// A vertex of a [`ModelMesh`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ModelVertex {
+    /// The position in model space, with the node transforms applied.
+    pub position: [f32; 3],
+    /// The unit normal, computed from the triangles if the file has none.
+    pub normal: [f32; 3],
+    /// The texture coordinates, with (0, 0) at the top left of the texture.
+    pub uv: [f32; 2],
+}
+
+impl ModelVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x2];
+
+    /// The layout of a vertex buffer containing `ModelVertex`es, matching the
+    /// `VertexInput` of `model.wgsl`.
+    pub const LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: size_of::<ModelVertex>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &Self::ATTRIBUTES,
+    };
+}
+
+/// The pixels of a texture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextureData {
+    pub width: u32,
+    pub height: u32,
+    /// Tightly packed, sRGB encoded RGBA8 pixels.
+    pub pixels: Vec<u8>,
+}
+
+/// How the surface of a [`ModelMesh`] is colored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material {
+    /// The linear RGBA color, which is multiplied with the texture.
+    pub base_color: [f32; 4],
+    /// Shared between the meshes that use the same texture.
+    pub base_color_texture: Option<Arc<TextureData>>,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            base_color: [1.0; 4],
+            base_color_texture: None,
+        }
+    }
+}
+
+/// Indexed triangles with a single material.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ModelMesh {
+    pub vertices: Vec<ModelVertex>,
+    pub indices: Vec<u32>,
+    pub material: Material,
+}
+
+/// A static model, made up of one or more meshes.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Model {
+    pub meshes: Vec<ModelMesh>,
+}
+
+/// Why a model could not be loaded.
+#[derive(Debug)]
+pub enum ModelError {
+    /// The file extension is not one of `gltf`, `glb` or `obj`.
+    UnsupportedExtension(PathBuf),
+    Gltf(gltf::Error),
+    Obj(tobj::LoadError),
+    Texture {
+        path: PathBuf,
+        source: image::ImageError,
+    },
+    /// A glTF primitive has no positions.
+    MissingPositions,
+    /// A mesh has an index that is out of bounds of its vertices.
+    IndexOutOfBounds {
+        index: u32,
+        vertex_count: usize,
+    },
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedExtension(path) => {
+                write!(
+                    f,
+                    "unsupported model {}, expected a .gltf, .glb or .obj file",
+                    path.display()
+                )
+            }
+            Self::Gltf(err) => write!(f, "failed loading glTF: {err}"),
+            Self::Obj(err) => write!(f, "failed loading OBJ: {err}"),
+            Self::Texture { path, source } => {
+                write!(f, "failed loading texture {}: {source}", path.display())
+            }
+            Self::MissingPositions => write!(f, "glTF primitive has no positions"),
+            Self::IndexOutOfBounds {
+                index,
+                vertex_count,
+            } => write!(
+                f,
+                "index {index} is out of bounds of the {vertex_count} vertices of the mesh"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ModelError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Gltf(err) => Some(err),
+            Self::Obj(err) => Some(err),
+            Self::Texture { source, .. } => Some(source),
+            Self::UnsupportedExtension(_)
+            | Self::MissingPositions
+            | Self::IndexOutOfBounds { .. } => None,
+        }
+    }
+}
+
+impl From<gltf::Error> for ModelError {
+    fn from(err: gltf::Error) -> Self {
+        Self::Gltf(err)
+    }
+}
+
+impl From<tobj::LoadError> for ModelError {
+    fn from(err: tobj::LoadError) -> Self {
+        Self::Obj(err)
+    }
+}
+
+impl Model {
+    /// Load a model, with the format chosen by the file extension.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ModelError> {
+        let path = path.as_ref();
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        match extension.map(str::to_ascii_lowercase).as_deref() {
+            Some("gltf" | "glb") => Self::load_gltf(path),
+            Some("obj") => Self::load_obj(path),
+            _ => Err(ModelError::UnsupportedExtension(path.to_path_buf())),
+        }
+    }
+
+    /// Load the default scene of a glTF 2.0 file, with the buffers and
+    /// images either embedded, in the binary chunk of a `.glb`, or in
+    /// separate files next to it.
+    ///
+    /// Only triangle primitives are loaded, and only the base color of the
+    /// materials is used, with the texture coordinates of the set that the
+    /// base color texture uses.
+    pub fn load_gltf(path: impl AsRef<Path>) -> Result<Self, ModelError> {
+        let (document, buffers, images) = gltf::import(path)?;
+        let mut loader = GltfLoader {
+            buffers: &buffers,
+            images: &images,
+            textures: HashMap::new(),
+            meshes: Vec::new(),
+        };
+        match document
+            .default_scene()
+            .or_else(|| document.scenes().next())
+        {
+            Some(scene) => {
+                for node in scene.nodes() {
+                    loader.load_node(&node, &math::IDENTITY)?;
+                }
+            }
+            // Without a scene, there are no transforms to apply.
+            None => {
+                for mesh in document.meshes() {
+                    loader.load_mesh(&mesh, &math::IDENTITY)?;
+                }
+            }
+        }
+        Ok(Self {
+            meshes: loader.meshes,
+        })
+    }
+
+    /// Load a Wavefront OBJ file, and the materials and textures that it
+    /// references.
+    ///
+    /// Faces are triangulated, and only the diffuse color and texture of the
+    /// materials are used. If the materials cannot be loaded, the meshes are
+    /// loaded without them.
+    pub fn load_obj(path: impl AsRef<Path>) -> Result<Self, ModelError> {
+        let path = path.as_ref();
+        let (models, materials) = tobj::load_obj(path, &tobj::GPU_LOAD_OPTIONS)?;
+        let materials = materials.unwrap_or_else(|err| {
+            tracing::warn!(%err, path = %path.display(), "failed loading OBJ materials");
+            Vec::new()
+        });
+
+        // Texture paths are relative to the OBJ file.
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let mut textures = HashMap::new();
+        let materials = materials
+            .iter()
+            .map(|material| {
+                let [r, g, b] = material.diffuse.unwrap_or([1.0; 3]);
+                let base_color_texture = match &material.diffuse_texture {
+                    Some(texture) => Some(load_texture(&dir.join(texture), &mut textures)?),
+                    None => None,
+                };
+                Ok(Material {
+                    base_color: [r, g, b, material.dissolve.unwrap_or(1.0)],
+                    base_color_texture,
+                })
+            })
+            .collect::<Result<Vec<_>, ModelError>>()?;
+
+        let meshes = models
+            .into_iter()
+            .map(|model| {
+                let mesh = model.mesh;
+                let positions: Vec<Vec3> = mesh
+                    .positions
+                    .chunks_exact(3)
+                    .map(|p| [p[0], p[1], p[2]])
+                    .collect();
+                let normals = mesh.normals.chunks_exact(3).map(|n| [n[0], n[1], n[2]]);
+                // OBJ texture coordinates start at the bottom left.
+                let uvs = mesh.texcoords.chunks_exact(2).map(|t| [t[0], 1.0 - t[1]]);
+                let material = mesh
+                    .material_id
+                    .and_then(|id| materials.get(id))
+                    .cloned()
+                    .unwrap_or_default();
+                build_mesh(&positions, normals, uvs, mesh.indices, material)
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { meshes })
+    }
+
+    /// The corners of the axis-aligned box around all the vertices, or
+    /// `None` if there are no vertices.
+    pub fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        self.meshes
+            .iter()
+            .flat_map(|mesh| &mesh.vertices)
+            .map(|vertex| (vertex.position, vertex.position))
+            .reduce(|(min, max), (p, _)| {
+                (
+                    [0, 1, 2].map(|i| min[i].min(p[i])),
+                    [0, 1, 2].map(|i| max[i].max(p[i])),
+                )
+            })
+    }
+}
+
+struct GltfLoader<'a> {
+    buffers: &'a [gltf::buffer::Data],
+    images: &'a [gltf::image::Data],
+    // Converted images by index, `None` if the format is not supported.
+    textures: HashMap<usize, Option<Arc<TextureData>>>,
+    meshes: Vec<ModelMesh>,
+}
+
+impl GltfLoader<'_> {
+    fn load_node(&mut self, node: &gltf::Node<'_>, parent: &Mat4) -> Result<(), ModelError> {
+        let transform = math::mul(parent, &node.transform().matrix());
+        if let Some(mesh) = node.mesh() {
+            self.load_mesh(&mesh, &transform)?;
+        }
+        for child in node.children() {
+            self.load_node(&child, &transform)?;
+        }
+        Ok(())
+    }
+
+    fn load_mesh(&mut self, mesh: &gltf::Mesh<'_>, transform: &Mat4) -> Result<(), ModelError> {
+        for primitive in mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                tracing::warn!(mode = ?primitive.mode(), "skipping non-triangle glTF primitive");
+                continue;
+            }
+            let reader = primitive.reader(|buffer| Some(&self.buffers[buffer.index()]));
+            let positions: Vec<Vec3> = reader
+                .read_positions()
+                .ok_or(ModelError::MissingPositions)?
+                .map(|p| math::transform_point(transform, p))
+                .collect();
+            let normals = reader
+                .read_normals()
+                .into_iter()
+                .flatten()
+                .map(|n| math::transform_normal(transform, n));
+            let indices = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..positions.len() as u32).collect(),
+            };
+
+            let pbr = primitive.material().pbr_metallic_roughness();
+            let base_color_texture_info = pbr.base_color_texture();
+            // The texture may use any set of texture coordinates.
+            let tex_coord = base_color_texture_info
+                .as_ref()
+                .map_or(0, |info| info.tex_coord());
+            let uvs = reader
+                .read_tex_coords(tex_coord)
+                .into_iter()
+                .flat_map(|uvs| uvs.into_f32());
+            let base_color_texture = base_color_texture_info
+                .and_then(|info| self.texture(info.texture().source().index()));
+            let material = Material {
+                base_color: pbr.base_color_factor(),
+                base_color_texture,
+            };
+            let mesh = build_mesh(&positions, normals, uvs, indices, material)?;
+            self.meshes.push(mesh);
+        }
+        Ok(())
+    }
+
+    fn texture(&mut self, index: usize) -> Option<Arc<TextureData>> {
+        let images = self.images;
+        self.textures
+            .entry(index)
+            .or_insert_with(|| {
+                let image = &images[index];
+                let pixels = rgba8(image);
+                if pixels.is_none() {
+                    tracing::warn!(format = ?image.format, "unsupported glTF texture format");
+                }
+                pixels.map(|pixels| {
+                    Arc::new(TextureData {
+                        width: image.width,
+                        height: image.height,
+                        pixels,
+                    })
+                })
+            })
+            .clone()
+    }
+}
+
+/// Convert 8-bit glTF images to RGBA8, where one and two channel images are
+/// grey, as decoded from PNGs.
+fn rgba8(image: &gltf::image::Data) -> Option<Vec<u8>> {
+    use gltf::image::Format;
+    let pixels = &image.pixels;
+    Some(match image.format {
+        Format::R8 => pixels.iter().flat_map(|&r| [r, r, r, 255]).collect(),
+        // Grey with alpha, like PNGs with a luma and an alpha channel.
+        Format::R8G8 => pixels
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        Format::R8G8B8 => pixels
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        Format::R8G8B8A8 => pixels.clone(),
+        _ => return None,
+    })
+}
+
+/// Load an image file as a texture, or reuse it if it was already loaded.
+fn load_texture(
+    path: &Path,
+    textures: &mut HashMap<PathBuf, Arc<TextureData>>,
+) -> Result<Arc<TextureData>, ModelError> {
+    if let Some(texture) = textures.get(path) {
+        return Ok(Arc::clone(texture));
+    }
+    let image = image::open(path)
+        .map_err(|source| ModelError::Texture {
+            path: path.to_path_buf(),
+            source,
+        })?
+        .into_rgba8();
+    let texture = Arc::new(TextureData {
+        width: image.width(),
+        height: image.height(),
+        pixels: image.into_raw(),
+    });
+    textures.insert(path.to_path_buf(), Arc::clone(&texture));
+    Ok(texture)
+}
+
+/// Combine the vertex attributes, where the normals and texture coordinates
+/// may be missing, after checking that the indices are in bounds.
+fn build_mesh(
+    positions: &[Vec3],
+    normals: impl Iterator<Item = Vec3>,
+    uvs: impl Iterator<Item = [f32; 2]>,
+    indices: Vec<u32>,
+    material: Material,
+) -> Result<ModelMesh, ModelError> {
+    if let Some(&index) = indices
+        .iter()
+        .find(|&&index| index as usize >= positions.len())
+    {
+        return Err(ModelError::IndexOutOfBounds {
+            index,
+            vertex_count: positions.len(),
+        });
+    }
+    let mut normals: Vec<Vec3> = normals.collect();
+    if normals.len() != positions.len() {
+        normals = smooth_normals(positions, &indices);
+    }
+    let uvs: Vec<[f32; 2]> = uvs.collect();
+    let vertices = positions
+        .iter()
+        .zip(normals)
+        .enumerate()
+        .map(|(i, (&position, normal))| ModelVertex {
+            position,
+            normal,
+            uv: uvs.get(i).copied().unwrap_or_default(),
+        })
+        .collect();
+    Ok(ModelMesh {
+        vertices,
+        indices,
+        material,
+    })
+}
+
+/// The area-weighted average of the normals of the triangles around each
+/// vertex, where the indices must be in bounds of `positions`.
+fn smooth_normals(positions: &[Vec3], indices: &[u32]) -> Vec<Vec3> {
+    let mut normals = vec![[0.0; 3]; positions.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| positions[triangle[i] as usize]);
+        let normal = math::cross(math::sub(b, a), math::sub(c, a));
+        for &index in triangle {
+            let sum = &mut normals[index as usize];
+            *sum = [0, 1, 2].map(|i| sum[i] + normal[i]);
+        }
+    }
+    normals
+        .into_iter()
+        .map(|normal| math::normalize(normal).unwrap_or([0.0, 0.0, 1.0]))
+        .collect()
+}
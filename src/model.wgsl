@@ -0,0 +1,50 @@
+// Lambertian shading of a base color, lit by a single directional light.
+struct Camera {
+    view_proj: mat4x4<f32>,
+    // The direction towards the light, in the `xyz` components.
+    light_dir: vec4<f32>,
+}
+
+struct Material {
+    base_color: vec4<f32>,
+}
+
+@group(1) @binding(0) var<uniform> camera: Camera;
+
+@group(2) @binding(0) var<uniform> material: Material;
+@group(2) @binding(1) var base_color_texture: texture_2d<f32>;
+@group(2) @binding(2) var base_color_sampler: sampler;
+
+// The vertex attributes match `ModelVertex::LAYOUT` in `model.rs`.
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) normal: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+}
+
+// The fraction of the light that also reaches the surfaces facing away.
+const AMBIENT: f32 = 0.25;
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.position = camera.view_proj * vec4<f32>(in.position, 1.0);
+    out.normal = in.normal;
+    out.uv = in.uv;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput, @builtin(front_facing) front_facing: bool) -> @location(0) vec4<f32> {
+    // Light both sides of open meshes.
+    let normal = select(-1.0, 1.0, front_facing) * normalize(in.normal);
+    let diffuse = max(dot(normal, normalize(camera.light_dir.xyz)), 0.0);
+    let color = material.base_color * textureSample(base_color_texture, base_color_sampler, in.uv);
+    return vec4<f32>(color.rgb * (AMBIENT + (1.0 - AMBIENT) * diffuse), color.a);
+}
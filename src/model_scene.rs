@@ -0,0 +1,327 @@
+//! Drawing a loaded [`Model`] with simple lighting.
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use wgpu::util::DeviceExt;
+
+use crate::gpu_context::GpuContext;
+use crate::math::{self, Mat4};
+use crate::model::{Model, ModelVertex, TextureData};
+use crate::scene::{Scene, TargetFormat};
+
+/// The vertical field of view of the camera, in radians.
+const FOV_Y: f32 = std::f32::consts::FRAC_PI_4;
+
+/// The direction from the model towards the camera.
+const VIEW_DIR: [f32; 3] = [0.4, 0.5, 1.0];
+
+/// The direction towards the light, from above and to the left of the
+/// camera.
+const LIGHT_DIR: [f32; 3] = [-0.3, 0.8, 0.6];
+
+/// Matches the `Camera` struct in `model.wgsl`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct CameraUniform {
+    view_proj: Mat4,
+    light_dir: [f32; 4],
+}
+
+/// Matches the `Material` struct in `model.wgsl`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct MaterialUniform {
+    base_color: [f32; 4],
+}
+
+/// Draws a static [`Model`], lit by a directional light.
+///
+/// The camera looks at the model from the front, slightly above and to the
+/// right, at a distance where the whole model is visible.
+#[derive(Debug)]
+pub struct ModelScene {
+    model: Model,
+    // `None` until initialized.
+    resources: Option<Resources>,
+}
+
+/// The resources that must be recreated along with the device.
+#[derive(Debug)]
+struct Resources {
+    render_pipeline: wgpu::RenderPipeline,
+    camera_buf: wgpu::Buffer,
+    camera_bind_group: wgpu::BindGroup,
+    meshes: Vec<GpuMesh>,
+}
+
+/// The buffers of a mesh, and the bind group of its material.
+#[derive(Debug)]
+struct GpuMesh {
+    vertex_buf: wgpu::Buffer,
+    index_buf: wgpu::Buffer,
+    index_count: u32,
+    material_bind_group: wgpu::BindGroup,
+}
+
+impl ModelScene {
+    pub fn new(model: Model) -> Self {
+        Self {
+            model,
+            resources: None,
+        }
+    }
+
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+}
+
+impl Scene for ModelScene {
+    fn init(&mut self, gpu: &GpuContext, format: TargetFormat) {
+        let device = gpu.device();
+        let camera_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Camera Bind Group Layout"),
+            entries: &[uniform_entry(0, size_of::<CameraUniform>())],
+        });
+        let material_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Material Bind Group Layout"),
+            entries: &[
+                uniform_entry(0, size_of::<MaterialUniform>()),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = gpu.create_shader_module("Model Shader", include_str!("model.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                gpu.globals_bind_group_layout(),
+                &camera_layout,
+                &material_layout,
+            ],
+            immediate_size: 0,
+        });
+        let render_pipeline = gpu.create_render_pipeline(
+            &format!("Model {format:?}"),
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("Model Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[ModelVertex::LAYOUT],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some("fs_main"),
+                    compilation_options: Default::default(),
+                    targets: &[Some(format.format.into())],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: format.depth_stencil(),
+                multisample: format.multisample(),
+                multiview_mask: None,
+                cache: None,
+            },
+        );
+
+        // Written on resize, once the aspect ratio is known.
+        let camera_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Camera Buffer"),
+            size: size_of::<CameraUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &camera_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buf.as_entire_binding(),
+            }],
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        // Untextured materials sample a white texture, to use a single
+        // pipeline for both.
+        let white = create_texture(
+            gpu,
+            &TextureData {
+                width: 1,
+                height: 1,
+                pixels: vec![255; 4],
+            },
+        );
+        // Upload each texture once, even if it is used by several meshes.
+        let mut textures = HashMap::new();
+
+        let meshes = self
+            .model
+            .meshes
+            .iter()
+            .filter(|mesh| !mesh.indices.is_empty())
+            .map(|mesh| {
+                let material = &mesh.material;
+                let texture = match &material.base_color_texture {
+                    Some(texture) => textures
+                        .entry(Arc::as_ptr(texture))
+                        .or_insert_with(|| create_texture(gpu, texture)),
+                    None => &white,
+                };
+                let material_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Material Buffer"),
+                    contents: bytemuck::bytes_of(&MaterialUniform {
+                        base_color: material.base_color,
+                    }),
+                    usage: wgpu::BufferUsages::UNIFORM,
+                });
+                let material_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: None,
+                    layout: &material_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: material_buf.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::TextureView(texture),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: wgpu::BindingResource::Sampler(&sampler),
+                        },
+                    ],
+                });
+                GpuMesh {
+                    vertex_buf: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("Model Vertex Buffer"),
+                        contents: bytemuck::cast_slice(&mesh.vertices),
+                        usage: wgpu::BufferUsages::VERTEX,
+                    }),
+                    index_buf: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("Model Index Buffer"),
+                        contents: bytemuck::cast_slice(&mesh.indices),
+                        usage: wgpu::BufferUsages::INDEX,
+                    }),
+                    index_count: mesh.indices.len() as u32,
+                    material_bind_group,
+                }
+            })
+            .collect();
+
+        self.resources = Some(Resources {
+            render_pipeline,
+            camera_buf,
+            camera_bind_group,
+            meshes,
+        });
+    }
+
+    fn resize(&mut self, gpu: &GpuContext, width: u32, height: u32, _scale_factor: f32) {
+        let Some(resources) = &self.resources else {
+            return;
+        };
+        let aspect = width.max(1) as f32 / height.max(1) as f32;
+        let camera = CameraUniform {
+            view_proj: view_proj(&self.model, aspect),
+            light_dir: [LIGHT_DIR[0], LIGHT_DIR[1], LIGHT_DIR[2], 0.0],
+        };
+        gpu.queue()
+            .write_buffer(&resources.camera_buf, 0, bytemuck::bytes_of(&camera));
+    }
+
+    fn update(&mut self, _gpu: &GpuContext, _dt: Duration) {}
+
+    fn render(&mut self, rpass: &mut wgpu::RenderPass<'_>) {
+        let Some(resources) = &self.resources else {
+            return;
+        };
+        rpass.set_pipeline(&resources.render_pipeline);
+        rpass.set_bind_group(1, &resources.camera_bind_group, &[]);
+        for mesh in &resources.meshes {
+            rpass.set_bind_group(2, &mesh.material_bind_group, &[]);
+            rpass.set_vertex_buffer(0, mesh.vertex_buf.slice(..));
+            rpass.set_index_buffer(mesh.index_buf.slice(..), wgpu::IndexFormat::Uint32);
+            rpass.draw_indexed(0..mesh.index_count, 0, 0..1);
+        }
+    }
+}
+
+fn uniform_entry(binding: u32, size: usize) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: wgpu::BufferSize::new(size as u64),
+        },
+        count: None,
+    }
+}
+
+fn create_texture(gpu: &GpuContext, texture: &TextureData) -> wgpu::TextureView {
+    gpu.device()
+        .create_texture_with_data(
+            gpu.queue(),
+            &wgpu::TextureDescriptor {
+                label: Some("Base Color Texture"),
+                size: wgpu::Extent3d {
+                    width: texture.width,
+                    height: texture.height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            &texture.pixels,
+        )
+        .create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+/// A camera that fits the bounding sphere of the model into the view.
+fn view_proj(model: &Model, aspect: f32) -> Mat4 {
+    let (min, max) = model.bounds().unwrap_or(([-1.0; 3], [1.0; 3]));
+    let center = [0, 1, 2].map(|i| (min[i] + max[i]) / 2.0);
+    let radius = (math::dot(math::sub(max, min), math::sub(max, min)).sqrt() / 2.0).max(1e-3);
+
+    // Fit the sphere into the narrower of the two fields of view.
+    let fov_x = 2.0 * ((FOV_Y / 2.0).tan() * aspect).atan();
+    let distance = radius / (FOV_Y.min(fov_x) / 2.0).sin();
+    let view_dir = math::normalize(VIEW_DIR).unwrap();
+    let eye = [0, 1, 2].map(|i| center[i] + distance * view_dir[i]);
+
+    let view = math::look_at(eye, center, [0.0, 1.0, 0.0]);
+    let near = (distance - radius).max(distance * 0.01);
+    let proj = math::perspective(FOV_Y, aspect, near, distance + radius);
+    math::mul(&proj, &view)
+}
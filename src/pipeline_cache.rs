@@ -0,0 +1,304 @@
+//! Persisting compiled pipelines across launches.
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// A [`wgpu::PipelineCache`] that is loaded from, and saved to, a file.
+///
+/// Along with the cache data, the file stores how long each kind of
+/// pipeline took to create with and without cached data, so that we can log
+/// how much time the cache saved. See [`CacheFileContents`] for the format.
+#[derive(Debug)]
+pub struct PipelineCacheFile {
+    cache: wgpu::PipelineCache,
+    path: PathBuf,
+    timings: HashMap<String, PipelineTimings>,
+    /// Whether `cache` was created from data in the file, which the driver
+    /// accepted.
+    warm: bool,
+    /// The keys of the pipelines created by this process, which have
+    /// already been logged.
+    created: HashSet<String>,
+}
+
+/// How long creating a kind of pipeline took.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PipelineTimings {
+    /// Without any cached data, `None` if not measured yet.
+    pub cold: Option<Duration>,
+    /// With the data of an earlier launch, `None` if not measured yet.
+    pub warm: Option<Duration>,
+}
+
+/// The contents of a pipeline cache file.
+///
+/// The file starts with the length of the header as a little-endian `u32`,
+/// followed by the header, and then the data returned by
+/// [`wgpu::PipelineCache::get_data`]. The header is UTF-8, with a line of
+/// tab-separated key, cold and warm microseconds for each kind of pipeline,
+/// where a missing duration is left empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheFileContents {
+    pub timings: HashMap<String, PipelineTimings>,
+    pub data: Vec<u8>,
+}
+
+/// Why a pipeline cache file could not be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheFileError {
+    /// The file ends before the end of the header.
+    Truncated,
+    /// The header is not in the expected format.
+    InvalidHeader,
+}
+
+impl fmt::Display for CacheFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated => f.write_str("pipeline cache file is truncated"),
+            Self::InvalidHeader => f.write_str("pipeline cache file has an invalid header"),
+        }
+    }
+}
+
+impl std::error::Error for CacheFileError {}
+
+/// The size of the length that the header starts with, in bytes.
+const HEADER_LENGTH_SIZE: usize = 4;
+
+impl CacheFileContents {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // Sorted, so that the same contents always produce the same file.
+        let mut keys: Vec<_> = self.timings.keys().collect();
+        keys.sort();
+        let mut header = String::new();
+        for key in keys {
+            let timings = &self.timings[key];
+            let micros = |duration: Option<Duration>| {
+                duration.map_or(String::new(), |duration| duration.as_micros().to_string())
+            };
+            writeln!(
+                header,
+                "{key}\t{}\t{}",
+                micros(timings.cold),
+                micros(timings.warm)
+            )
+            .unwrap();
+        }
+
+        let mut bytes = Vec::with_capacity(HEADER_LENGTH_SIZE + header.len() + self.data.len());
+        bytes.extend_from_slice(&(header.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        bytes.extend_from_slice(&self.data);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CacheFileError> {
+        let (length, rest) = bytes
+            .split_first_chunk::<HEADER_LENGTH_SIZE>()
+            .ok_or(CacheFileError::Truncated)?;
+        let length = u32::from_le_bytes(*length) as usize;
+        if rest.len() < length {
+            return Err(CacheFileError::Truncated);
+        }
+        let (header, data) = rest.split_at(length);
+        let header = std::str::from_utf8(header).map_err(|_| CacheFileError::InvalidHeader)?;
+
+        let parse_micros = |micros: &str| match micros {
+            "" => Ok(None),
+            micros => micros
+                .parse()
+                .map(|micros| Some(Duration::from_micros(micros)))
+                .map_err(|_| CacheFileError::InvalidHeader),
+        };
+        let mut timings = HashMap::new();
+        for line in header.lines() {
+            let [key, cold, warm] = line
+                .split('\t')
+                .collect::<Vec<_>>()
+                .try_into()
+                .map_err(|_| CacheFileError::InvalidHeader)?;
+            let pipeline_timings = PipelineTimings {
+                cold: parse_micros(cold)?,
+                warm: parse_micros(warm)?,
+            };
+            timings.insert(key.to_string(), pipeline_timings);
+        }
+
+        Ok(Self {
+            timings,
+            data: data.to_vec(),
+        })
+    }
+}
+
+impl PipelineCacheFile {
+    /// Load the cache for the given adapter from disk.
+    ///
+    /// Returns `None` if the device does not support pipeline caches.
+    pub fn load(device: &wgpu::Device, adapter: &wgpu::Adapter) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::PIPELINE_CACHE) {
+            tracing::info!("pipeline caches are not supported by the device, not caching");
+            return None;
+        }
+        let Some(key) = wgpu::util::pipeline_cache_key(&adapter.get_info()) else {
+            tracing::info!("pipeline caches are not supported by the backend, not caching");
+            return None;
+        };
+        let Some(dir) = cache_dir() else {
+            tracing::warn!("could not find cache directory, not caching pipelines");
+            return None;
+        };
+        let path = dir.join(format!("{key}_v{}.bin", env!("CARGO_PKG_VERSION")));
+
+        let contents = match std::fs::read(&path) {
+            Ok(bytes) => match CacheFileContents::from_bytes(&bytes) {
+                Ok(contents) => contents,
+                Err(err) => {
+                    tracing::warn!(path = %path.display(), %err, "ignoring pipeline cache file");
+                    CacheFileContents::default()
+                }
+            },
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                tracing::info!(path = %path.display(), "no pipeline cache file yet");
+                CacheFileContents::default()
+            }
+            Err(err) => {
+                tracing::warn!(path = %path.display(), %err, "failed reading pipeline cache");
+                CacheFileContents::default()
+            }
+        };
+
+        let accepted = (!contents.data.is_empty())
+            .then(|| {
+                // Without `fallback`, rejected data is reported as a
+                // validation error, so that we can tell that the cache is
+                // empty after all.
+                let error_scope = device.push_error_scope(wgpu::ErrorFilter::Validation);
+                // SAFETY: The data was returned from `PipelineCache::get_data`
+                // in `save`, and the file is keyed with `pipeline_cache_key`,
+                // so it was produced by a compatible adapter.
+                let cache = unsafe { create_pipeline_cache(device, Some(&contents.data)) };
+                match pollster::block_on(error_scope.pop()) {
+                    None => Some(cache),
+                    Some(err) => {
+                        tracing::warn!(
+                            path = %path.display(),
+                            %err,
+                            "pipeline cache data was rejected, starting with an empty cache"
+                        );
+                        None
+                    }
+                }
+            })
+            .flatten();
+        let warm = accepted.is_some();
+        // SAFETY: There is no data.
+        let cache = accepted.unwrap_or_else(|| unsafe { create_pipeline_cache(device, None) });
+        tracing::debug!(
+            path = %path.display(),
+            loaded_bytes = if warm { contents.data.len() } else { 0 },
+            "created pipeline cache"
+        );
+
+        Some(Self {
+            cache,
+            path,
+            timings: contents.timings,
+            warm,
+            created: HashSet::new(),
+        })
+    }
+
+    pub fn cache(&self) -> &wgpu::PipelineCache {
+        &self.cache
+    }
+
+    /// Log how long creating the pipeline identified by `key` took, and
+    /// write the cache back to disk.
+    ///
+    /// The key must be the same for the same pipeline across launches, and
+    /// must not contain tabs or newlines. Only the first creation of each key
+    /// is logged, recorded and saved, as later ones are served from the
+    /// in-memory cache, and saving is too slow to do whenever a scene
+    /// recreates its pipelines.
+    pub fn pipeline_created(&mut self, key: &str, duration: Duration) {
+        if !self.created.insert(key.to_string()) {
+            return;
+        }
+        let timings = self.timings.entry(key.to_string()).or_default();
+        match timings.cold {
+            // The data from the earlier launch includes this pipeline.
+            Some(cold) if self.warm => {
+                timings.warm = Some(duration);
+                tracing::info!(
+                    key,
+                    ?duration,
+                    ?cold,
+                    saved = ?cold.saturating_sub(duration),
+                    "created pipeline using the pipeline cache"
+                );
+            }
+            _ => {
+                timings.cold = Some(duration);
+                tracing::info!(key, ?duration, "created pipeline without cached data");
+            }
+        }
+
+        if let Err(err) = self.save() {
+            tracing::warn!(path = %self.path.display(), %err, "failed saving pipeline cache");
+        }
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        let Some(data) = self.cache.get_data() else {
+            return Ok(());
+        };
+        let contents = CacheFileContents {
+            timings: self.timings.clone(),
+            data,
+        };
+
+        // Write to a temporary file first, so that we never leave a partially
+        // written cache behind.
+        std::fs::create_dir_all(self.path.parent().unwrap())?;
+        let temp_path = self.path.with_extension("tmp");
+        std::fs::write(&temp_path, contents.to_bytes())?;
+        std::fs::rename(&temp_path, &self.path)?;
+        tracing::debug!(
+            path = %self.path.display(),
+            bytes = contents.data.len(),
+            "saved pipeline cache"
+        );
+        Ok(())
+    }
+}
+
+/// # Safety
+///
+/// `data` must have been returned from [`wgpu::PipelineCache::get_data`].
+unsafe fn create_pipeline_cache(device: &wgpu::Device, data: Option<&[u8]>) -> wgpu::PipelineCache {
+    unsafe {
+        device.create_pipeline_cache(&wgpu::PipelineCacheDescriptor {
+            label: Some("Pipeline Cache"),
+            data,
+            fallback: false,
+        })
+    }
+}
+
+/// The directory to store the cache files in.
+fn cache_dir() -> Option<PathBuf> {
+    let base = if let Some(dir) = std::env::var_os("XDG_CACHE_HOME") {
+        PathBuf::from(dir)
+    } else {
+        let home = PathBuf::from(std::env::var_os("HOME")?);
+        if cfg!(target_vendor = "apple") {
+            home.join("Library/Caches")
+        } else {
+            home.join(".cache")
+        }
+    };
+    Some(base.join(Path::new(env!("CARGO_PKG_NAME"))))
+}
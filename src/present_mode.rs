@@ -0,0 +1,36 @@
+//! Negotiating the present mode with the surface.
+
+use wgpu::PresentMode;
+
+/// Present without waiting for VSync, in order of preference: tear-free
+/// first, then lowest latency.
+///
+/// Falls back to [`PresentMode::Fifo`], which is always supported.
+pub const NO_VSYNC: &[PresentMode] = &[
+    PresentMode::Mailbox,
+    PresentMode::Immediate,
+    PresentMode::FifoRelaxed,
+    PresentMode::Fifo,
+];
+
+/// Wait for VSync before presenting.
+pub const VSYNC: &[PresentMode] = &[PresentMode::Fifo];
+
+/// Pick the first of `preferences` that is in `supported`.
+///
+/// Falls back to [`PresentMode::Fifo`] if none of them are, since that is
+/// supported by every surface.
+pub fn negotiate(preferences: &[PresentMode], supported: &[PresentMode]) -> PresentMode {
+    let present_mode = preferences
+        .iter()
+        .copied()
+        .find(|mode| supported.contains(mode));
+    if present_mode.is_none() {
+        tracing::warn!(
+            ?preferences,
+            ?supported,
+            "no preferred present mode is supported, falling back to Fifo"
+        );
+    }
+    present_mode.unwrap_or(PresentMode::Fifo)
+}
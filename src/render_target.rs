@@ -0,0 +1,423 @@
+//! The different kinds of textures that a [`Renderer`] can render into.
+//!
+//! [`Renderer`]: crate::renderer::Renderer
+use std::cell::RefCell;
+use std::fmt;
+
+use crate::{alpha_mode, present_mode};
+
+/// Something that can provide a texture to render each frame into.
+pub trait RenderTarget: fmt::Debug {
+    /// The format of the textures returned by [`next_frame`].
+    ///
+    /// [`next_frame`]: RenderTarget::next_frame
+    fn format(&self) -> wgpu::TextureFormat;
+
+    /// The current size of the target, in pixels.
+    fn size(&self) -> (u32, u32);
+
+    /// Update the size of the target.
+    fn resize(&self, device: &wgpu::Device, width: u32, height: u32);
+
+    /// Get the next texture to render into.
+    fn next_frame(&self, device: &wgpu::Device) -> Result<Frame, AcquireError>;
+
+    /// The maximum number of frames that may be queued up for presentation,
+    /// or `None` if the frames are not presented.
+    fn maximum_frame_latency(&self) -> Option<u32> {
+        None
+    }
+
+    /// Whether colors must be premultiplied by their alpha before being
+    /// written to the frame.
+    fn premultiplied_alpha(&self) -> bool {
+        false
+    }
+
+    /// How the frames are presented, or `None` if they are not presented.
+    fn present_mode(&self) -> Option<wgpu::PresentMode> {
+        None
+    }
+}
+
+/// Why a frame could not be acquired from a [`RenderTarget`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcquireError {
+    /// Nothing can be rendered this frame, try again on the next one.
+    Skipped,
+    /// The device has run out of memory, and must be recovered before
+    /// rendering can continue.
+    DeviceRecoveryNeeded,
+}
+
+/// A texture acquired from a [`RenderTarget`].
+#[derive(Debug)]
+pub struct Frame {
+    view: wgpu::TextureView,
+    surface_texture: Option<wgpu::SurfaceTexture>,
+}
+
+impl Frame {
+    /// A frame that does not need to be presented.
+    pub fn from_view(view: wgpu::TextureView) -> Self {
+        Self {
+            view,
+            surface_texture: None,
+        }
+    }
+
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    /// Present the frame, if it came from a surface.
+    pub fn present(self) {
+        if let Some(surface_texture) = self.surface_texture {
+            surface_texture.present();
+        }
+    }
+}
+
+/// A surface backed by a window or view.
+#[derive(Debug)]
+pub struct WindowSurface<'window> {
+    surface: wgpu::Surface<'window>,
+    config: RefCell<wgpu::SurfaceConfiguration>,
+    supported_present_modes: Vec<wgpu::PresentMode>,
+    supported_alpha_modes: Vec<wgpu::CompositeAlphaMode>,
+}
+
+impl<'window> WindowSurface<'window> {
+    /// Configure `surface` with `config`.
+    ///
+    /// The present mode and alpha mode in `config` must be supported by the
+    /// surface, see [`present_mode::negotiate`] and [`alpha_mode::negotiate`].
+    pub fn new(
+        surface: wgpu::Surface<'window>,
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        config: wgpu::SurfaceConfiguration,
+    ) -> Self {
+        surface.configure(device, &config);
+        let capabilities = surface.get_capabilities(adapter);
+        Self {
+            surface,
+            config: RefCell::new(config),
+            supported_present_modes: capabilities.present_modes,
+            supported_alpha_modes: capabilities.alpha_modes,
+        }
+    }
+
+    /// Reconfigure the surface with the first of `preferences` that it
+    /// supports.
+    ///
+    /// Returns the present mode that is now in use.
+    pub fn set_present_mode(
+        &self,
+        device: &wgpu::Device,
+        preferences: &[wgpu::PresentMode],
+    ) -> wgpu::PresentMode {
+        let present_mode = present_mode::negotiate(preferences, &self.supported_present_modes);
+        let mut config = self.config.borrow_mut();
+        if config.present_mode != present_mode {
+            tracing::info!(?present_mode, "changing present mode");
+            config.present_mode = present_mode;
+            self.surface.configure(device, &config);
+        }
+        present_mode
+    }
+
+    pub fn alpha_mode(&self) -> wgpu::CompositeAlphaMode {
+        self.config.borrow().alpha_mode
+    }
+
+    /// Reconfigure the surface to be composited using its alpha channel, or
+    /// to be opaque, if supported.
+    ///
+    /// Returns the alpha mode that is now in use.
+    pub fn set_transparent(
+        &self,
+        device: &wgpu::Device,
+        transparent: bool,
+    ) -> wgpu::CompositeAlphaMode {
+        let alpha_mode = alpha_mode::negotiate(transparent, &self.supported_alpha_modes);
+        let mut config = self.config.borrow_mut();
+        if config.alpha_mode != alpha_mode {
+            tracing::info!(?alpha_mode, "changing alpha mode");
+            config.alpha_mode = alpha_mode;
+            self.surface.configure(device, &config);
+        }
+        alpha_mode
+    }
+
+    /// Reconfigure the surface with a new maximum frame latency.
+    ///
+    /// Lower values reduce the latency, at the cost of throughput.
+    pub fn set_maximum_frame_latency(&self, device: &wgpu::Device, latency: u32) {
+        let mut config = self.config.borrow_mut();
+        if config.desired_maximum_frame_latency != latency {
+            tracing::info!(latency, "changing maximum frame latency");
+            config.desired_maximum_frame_latency = latency;
+            self.surface.configure(device, &config);
+        }
+    }
+}
+
+impl RenderTarget for WindowSurface<'_> {
+    /// The format of the views rendered into, which may differ from the
+    /// format of the surface itself, see [`SurfaceFormat`].
+    ///
+    /// [`SurfaceFormat`]: crate::surface_format::SurfaceFormat
+    fn format(&self) -> wgpu::TextureFormat {
+        let config = self.config.borrow();
+        config
+            .view_formats
+            .first()
+            .copied()
+            .unwrap_or(config.format)
+    }
+
+    fn size(&self) -> (u32, u32) {
+        let config = self.config.borrow();
+        (config.width, config.height)
+    }
+
+    fn resize(&self, device: &wgpu::Device, width: u32, height: u32) {
+        let mut config = self.config.borrow_mut();
+        config.width = width;
+        config.height = height;
+        self.surface.configure(device, &config);
+    }
+
+    fn next_frame(&self, device: &wgpu::Device) -> Result<Frame, AcquireError> {
+        self.acquire(device, true)
+    }
+
+    fn maximum_frame_latency(&self) -> Option<u32> {
+        Some(self.config.borrow().desired_maximum_frame_latency)
+    }
+
+    fn premultiplied_alpha(&self) -> bool {
+        self.alpha_mode() == wgpu::CompositeAlphaMode::PreMultiplied
+    }
+
+    fn present_mode(&self) -> Option<wgpu::PresentMode> {
+        Some(self.config.borrow().present_mode)
+    }
+}
+
+impl WindowSurface<'_> {
+    fn acquire(&self, device: &wgpu::Device, may_retry: bool) -> Result<Frame, AcquireError> {
+        match self.surface.get_current_texture() {
+            Ok(surface_texture) => {
+                if !may_retry {
+                    tracing::info!("acquired frame after reconfiguring the surface");
+                }
+                Ok(Frame {
+                    view: surface_texture
+                        .texture
+                        .create_view(&wgpu::TextureViewDescriptor {
+                            format: Some(self.format()),
+                            ..Default::default()
+                        }),
+                    surface_texture: Some(surface_texture),
+                })
+            }
+            Err(err @ (wgpu::SurfaceError::Outdated | wgpu::SurfaceError::Lost)) => {
+                if may_retry {
+                    tracing::warn!(%err, "reconfiguring surface and retrying");
+                    self.surface.configure(device, &self.config.borrow());
+                    self.acquire(device, false)
+                } else {
+                    tracing::warn!(%err, "failed again after reconfiguring, skipping frame");
+                    Err(AcquireError::Skipped)
+                }
+            }
+            Err(err @ wgpu::SurfaceError::Timeout) => {
+                tracing::warn!(%err, "skipping frame");
+                Err(AcquireError::Skipped)
+            }
+            Err(err @ wgpu::SurfaceError::OutOfMemory) => {
+                tracing::error!(%err, "device needs recovery");
+                Err(AcquireError::DeviceRecoveryNeeded)
+            }
+            Err(err @ wgpu::SurfaceError::Other) => {
+                tracing::error!(%err, "skipping frame");
+                Err(AcquireError::Skipped)
+            }
+        }
+    }
+}
+
+/// An owned texture, whose contents can be read back with
+/// [`OffscreenTexture::read_back`].
+#[derive(Debug)]
+pub struct OffscreenTexture {
+    format: wgpu::TextureFormat,
+    texture: RefCell<wgpu::Texture>,
+}
+
+impl OffscreenTexture {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        Self {
+            format,
+            texture: RefCell::new(create_texture(device, format, width, height)),
+        }
+    }
+
+    /// Read back the contents of the texture as tightly packed pixels.
+    pub fn read_back(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Vec<u8> {
+        let texture = self.texture.borrow();
+        let width = texture.width();
+        let height = texture.height();
+        let bytes_per_pixel = self
+            .format
+            .block_copy_size(None)
+            .expect("color format has a block size");
+
+        // Rows in the staging buffer must be aligned.
+        let unpadded_bytes_per_row = width * bytes_per_pixel;
+        let padded_bytes_per_row =
+            unpadded_bytes_per_row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+
+        let staging_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: padded_bytes_per_row as u64 * height as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &staging_buf,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            texture.size(),
+        );
+        queue.submit(Some(encoder.finish()));
+
+        staging_buf.map_async(wgpu::MapMode::Read, .., |result| {
+            result.expect("Failed to map readback buffer")
+        });
+        device
+            .poll(wgpu::PollType::wait_indefinitely())
+            .expect("Failed to wait for readback");
+
+        let padded = staging_buf.get_mapped_range(..);
+        let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+        for row in padded.chunks_exact(padded_bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+        }
+        pixels
+    }
+}
+
+impl RenderTarget for OffscreenTexture {
+    fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    fn size(&self) -> (u32, u32) {
+        let texture = self.texture.borrow();
+        (texture.width(), texture.height())
+    }
+
+    fn resize(&self, device: &wgpu::Device, width: u32, height: u32) {
+        *self.texture.borrow_mut() = create_texture(device, self.format, width, height);
+    }
+
+    fn next_frame(&self, _device: &wgpu::Device) -> Result<Frame, AcquireError> {
+        let view = self
+            .texture
+            .borrow()
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        Ok(Frame::from_view(view))
+    }
+}
+
+fn create_texture(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Offscreen Texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+/// A texture view supplied by the caller, for example one owned by another
+/// part of a larger engine.
+///
+/// The caller is responsible for resizing the underlying texture, and for
+/// handing us the new view with [`ExternalView::set_view`].
+///
+/// The same goes for recreating the texture after the device is lost: the
+/// renderer switches to a new device on its own, but keeps rendering into
+/// the view from the lost device until it is replaced with one created from
+/// [`Renderer::gpu`].
+///
+/// [`Renderer::gpu`]: crate::renderer::Renderer::gpu
+#[derive(Debug)]
+pub struct ExternalView {
+    format: wgpu::TextureFormat,
+    view: RefCell<wgpu::TextureView>,
+}
+
+impl ExternalView {
+    pub fn new(view: wgpu::TextureView, format: wgpu::TextureFormat) -> Self {
+        Self {
+            format,
+            view: RefCell::new(view),
+        }
+    }
+
+    /// Replace the view that is rendered into.
+    ///
+    /// The new view must have the same format as the old one.
+    pub fn set_view(&self, view: wgpu::TextureView) {
+        *self.view.borrow_mut() = view;
+    }
+}
+
+impl RenderTarget for ExternalView {
+    fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    fn size(&self) -> (u32, u32) {
+        let view = self.view.borrow();
+        (view.texture().width(), view.texture().height())
+    }
+
+    fn resize(&self, _device: &wgpu::Device, _width: u32, _height: u32) {
+        // The view is owned by the caller, nothing to do.
+    }
+
+    fn next_frame(&self, _device: &wgpu::Device) -> Result<Frame, AcquireError> {
+        Ok(Frame::from_view(self.view.borrow().clone()))
+    }
+}
@@ -0,0 +1,681 @@
+//! Rendering a [`Scene`] into a [`RenderTarget`].
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::frame_counter::FrameCounter;
+use crate::globals::Globals;
+use crate::gpu_context::GpuContext;
+use crate::gpu_timer::GpuTimer;
+use crate::render_target::{AcquireError, OffscreenTexture, RenderTarget, WindowSurface};
+use crate::scene::{Scene, TargetFormat};
+use crate::stats_sink::StatsSink;
+use crate::surface_format::{FormatPreference, SurfaceFormat};
+use crate::trace_recorder;
+use crate::{alpha_mode, present_mode};
+
+/// The format used when rendering headlessly.
+const OFFSCREEN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// The format of the depth texture, unless changed with
+/// [`Renderer::set_depth_format`].
+pub const DEFAULT_DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// Why [`Renderer::set_depth_format`] rejected a format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepthFormatError {
+    /// The format has neither a depth nor a stencil aspect.
+    NotDepthStencil(wgpu::TextureFormat),
+    /// The format needs features that the device was not created with.
+    MissingFeatures {
+        format: wgpu::TextureFormat,
+        features: wgpu::Features,
+    },
+    /// The format can't be multisampled with the current sample count.
+    UnsupportedSampleCount {
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    },
+}
+
+impl fmt::Display for DepthFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotDepthStencil(format) => write!(f, "{format:?} is not a depth/stencil format"),
+            Self::MissingFeatures { format, features } => {
+                write!(f, "{format:?} requires the device features {features:?}")
+            }
+            Self::UnsupportedSampleCount {
+                format,
+                sample_count,
+            } => write!(
+                f,
+                "{format:?} does not support {sample_count} samples per pixel"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DepthFormatError {}
+
+/// Why [`Renderer::new`] could not render into a window.
+#[derive(Debug)]
+pub enum SurfaceError {
+    Create(wgpu::CreateSurfaceError),
+    /// The adapter of the context cannot present to the surface, for example
+    /// because it is a different GPU than the one driving the display.
+    Incompatible,
+}
+
+impl fmt::Display for SurfaceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Create(err) => write!(f, "failed creating surface: {err}"),
+            Self::Incompatible => f.write_str("adapter is not compatible with the surface"),
+        }
+    }
+}
+
+impl std::error::Error for SurfaceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Create(err) => Some(err),
+            Self::Incompatible => None,
+        }
+    }
+}
+
+/// Draws a [`Scene`] into a target, and keeps track of the frame statistics.
+#[derive(Debug)]
+pub struct Renderer<T> {
+    // Identifies the renderer in tracing spans, when there are several views.
+    id: u64,
+    // Replaced if the device is lost.
+    gpu: RefCell<Arc<GpuContext>>,
+    target: T,
+    scene: RefCell<Box<dyn Scene>>,
+    // `None` if timestamp queries are not supported. Recreated along with
+    // the device.
+    gpu_timer: RefCell<Option<GpuTimer>>,
+    // Recreated along with the device.
+    globals_binding: RefCell<GlobalsBinding>,
+    // The globals of the last frame.
+    globals: Cell<Globals>,
+    // When the renderer was created, which `Globals::time` is relative to.
+    created: Instant,
+    // In physical pixels from the top left, `None` if not over the view.
+    pointer: Cell<Option<[f32; 2]>>,
+    // Stored so that the scene can be resized after it is reinitialized.
+    scale_factor: Cell<f32>,
+    clear_color: Cell<wgpu::Color>,
+    sample_count: Cell<u32>,
+    depth_format: Cell<Option<wgpu::TextureFormat>>,
+    // Sized to match the target, and recreated along with it.
+    attachments: RefCell<Attachments>,
+    frame_counter: FrameCounter,
+    // The number of frames presented so far.
+    frame_index: Cell<u64>,
+    // When the scene was last updated, `None` before the first frame.
+    last_update: Cell<Option<Instant>>,
+}
+
+/// The uniform buffer of the [`Globals`], written before every frame.
+#[derive(Debug)]
+struct GlobalsBinding {
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl GlobalsBinding {
+    fn new(gpu: &GpuContext) -> Self {
+        let buffer = gpu.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Globals Buffer"),
+            size: size_of::<Globals>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = gpu.device().create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Globals"),
+            layout: gpu.globals_bind_group_layout(),
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+        Self { buffer, bind_group }
+    }
+}
+
+/// The textures rendered into along with the frame.
+#[derive(Debug, Default)]
+struct Attachments {
+    /// The multisampled texture that is resolved into the frame, `None` when
+    /// not multisampling.
+    msaa_view: Option<wgpu::TextureView>,
+    /// `None` when rendering without a depth texture.
+    depth_view: Option<wgpu::TextureView>,
+}
+
+impl<'window> Renderer<WindowSurface<'window>> {
+    /// Create a renderer that draws `scene` into a window's surface.
+    ///
+    /// Fails if the context's adapter cannot present to the window, since
+    /// the context is created before, and shared between, the windows.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        gpu: Arc<GpuContext>,
+        target: impl Into<wgpu::SurfaceTarget<'window>>,
+        width: u32,
+        height: u32,
+        scale_factor: f32,
+        present_modes: &[wgpu::PresentMode],
+        format_preference: FormatPreference,
+        scene: impl Scene + 'static,
+    ) -> Result<Self, SurfaceError> {
+        let surface = gpu
+            .instance()
+            .create_surface(target)
+            .map_err(SurfaceError::Create)?;
+
+        let mut config = surface
+            .get_default_config(gpu.adapter(), width, height)
+            .ok_or(SurfaceError::Incompatible)?;
+        let capabilities = surface.get_capabilities(gpu.adapter());
+
+        config.present_mode = present_mode::negotiate(present_modes, &capabilities.present_modes);
+        tracing::info!(present_mode = ?config.present_mode, "chose present mode");
+        config.alpha_mode = alpha_mode::negotiate(false, &capabilities.alpha_modes);
+
+        let format = SurfaceFormat::choose(&capabilities.formats, format_preference)
+            .expect("compatible surface has at least one format");
+        tracing::info!(
+            format = ?format.format,
+            view_format = ?format.view_format,
+            reason = format.reason,
+            "chose surface format"
+        );
+        config.format = format.format;
+        config.view_formats = format.view_formats();
+        let target = WindowSurface::new(surface, gpu.adapter(), gpu.device(), config);
+
+        Ok(Self::from_target(gpu, target, scale_factor, scene))
+    }
+
+    /// Switch to the first of the given present modes that the surface
+    /// supports, see [`present_mode::negotiate`].
+    ///
+    /// Returns the present mode that is now in use.
+    pub fn set_present_modes(&self, preferences: &[wgpu::PresentMode]) -> wgpu::PresentMode {
+        self.target
+            .set_present_mode(self.gpu.borrow().device(), preferences)
+    }
+
+    /// Composite the view with the content behind it, using the alpha of the
+    /// clear color (see [`Renderer::set_clear_color`]), or make it opaque.
+    ///
+    /// Returns the alpha mode that is now in use, which is opaque if the
+    /// surface does not support transparency.
+    pub fn set_transparent(&self, transparent: bool) -> wgpu::CompositeAlphaMode {
+        self.target
+            .set_transparent(self.gpu.borrow().device(), transparent)
+    }
+
+    /// Reconfigure the surface with a new maximum frame latency, i.e. the
+    /// number of frames that may be queued up for presentation.
+    pub fn set_maximum_frame_latency(&self, latency: u32) {
+        self.target
+            .set_maximum_frame_latency(self.gpu.borrow().device(), latency);
+        self.frame_counter
+            .set_frame_latency(self.target.maximum_frame_latency());
+    }
+}
+
+impl Renderer<OffscreenTexture> {
+    /// Create a renderer that renders into an owned texture instead of into
+    /// a view.
+    ///
+    /// This uses the fallback (software) adapter, and thus works without a
+    /// window or a GPU.
+    pub async fn new_headless(
+        width: u32,
+        height: u32,
+        scale_factor: f32,
+        scene: impl Scene + 'static,
+    ) -> Self {
+        let gpu = Arc::new(GpuContext::new_headless().await);
+        let target = OffscreenTexture::new(gpu.device(), OFFSCREEN_FORMAT, width, height);
+        Self::from_target(gpu, target, scale_factor, scene)
+    }
+
+    /// Read back the last rendered frame as tightly packed RGBA8 pixels.
+    pub fn read_frame(&self) -> Vec<u8> {
+        let gpu = self.gpu.borrow();
+        self.target.read_back(gpu.device(), gpu.queue())
+    }
+}
+
+impl<T: RenderTarget> Renderer<T> {
+    /// Create a renderer that draws `scene` into a custom target.
+    ///
+    /// The target must have been created with the context's device.
+    pub fn from_target(
+        gpu: Arc<GpuContext>,
+        target: T,
+        scale_factor: f32,
+        scene: impl Scene + 'static,
+    ) -> Self {
+        let gpu_timer = GpuTimer::new(gpu.device(), gpu.queue());
+        let globals_binding = GlobalsBinding::new(&gpu);
+        let frame_counter = FrameCounter::new();
+        frame_counter.set_frame_latency(target.maximum_frame_latency());
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        let this = Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            gpu: RefCell::new(gpu),
+            target,
+            scene: RefCell::new(Box::new(scene)),
+            gpu_timer: RefCell::new(gpu_timer),
+            globals_binding: RefCell::new(globals_binding),
+            globals: Cell::default(),
+            created: Instant::now(),
+            pointer: Cell::new(None),
+            scale_factor: Cell::new(scale_factor),
+            clear_color: Cell::new(wgpu::Color::GREEN),
+            sample_count: Cell::new(1),
+            depth_format: Cell::new(Some(DEFAULT_DEPTH_FORMAT)),
+            attachments: RefCell::default(),
+            frame_counter,
+            frame_index: Cell::new(0),
+            last_update: Cell::new(None),
+        };
+        this.recreate_attachments(this.gpu.borrow().device());
+        this.init_scene();
+        this
+    }
+
+    /// Replace the scene that is drawn.
+    pub fn set_scene(&self, scene: impl Scene + 'static) {
+        *self.scene.borrow_mut() = Box::new(scene);
+        self.last_update.set(None);
+        self.init_scene();
+    }
+
+    /// The formats that the scene renders into.
+    pub fn target_format(&self) -> TargetFormat {
+        TargetFormat {
+            format: self.target.format(),
+            sample_count: self.sample_count.get(),
+            depth_format: self.depth_format.get(),
+        }
+    }
+
+    /// (Re-)initialize the scene for the current device and target.
+    fn init_scene(&self) {
+        let gpu = self.gpu.borrow();
+        let mut scene = self.scene.borrow_mut();
+        scene.init(&gpu, self.target_format());
+        let (width, height) = self.target.size();
+        scene.resize(&gpu, width, height, self.scale_factor.get());
+    }
+
+    /// Set the position of the pointer in physical pixels from the top left
+    /// of the view, or `None` when it leaves the view.
+    ///
+    /// Used for [`Globals::pointer`] from the next frame on.
+    pub fn set_pointer_position(&self, position: Option<[f32; 2]>) {
+        self.pointer.set(position);
+    }
+
+    /// The globals that the last frame was rendered with.
+    pub fn globals(&self) -> Globals {
+        self.globals.get()
+    }
+
+    pub fn clear_color(&self) -> wgpu::Color {
+        self.clear_color.get()
+    }
+
+    /// Change the color that the frame is cleared with.
+    ///
+    /// The alpha is only used if the target is transparent, and should not be
+    /// premultiplied.
+    pub fn set_clear_color(&self, color: wgpu::Color) {
+        self.clear_color.set(color);
+    }
+
+    /// Set the refresh interval of the display, which is used to detect
+    /// frames that take noticeably longer than others.
+    pub fn set_refresh_interval(&self, refresh_interval: Duration) {
+        self.frame_counter.set_refresh_interval(refresh_interval);
+    }
+
+    /// Report the frame statistics to `sink`, instead of logging them.
+    pub fn set_stats_sink(&self, sink: Box<dyn StatsSink>) {
+        self.frame_counter.set_sink(sink);
+    }
+
+    /// The number of samples per pixel that is rendered with.
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count.get()
+    }
+
+    /// Change the number of samples per pixel, to smooth the edges.
+    ///
+    /// Falls back to a lower sample count if the requested one is not
+    /// supported by the adapter for the target's format and the depth format.
+    pub fn set_sample_count(&self, sample_count: u32) {
+        let gpu = self.gpu.borrow();
+        let formats: Vec<_> = [Some(self.target.format()), self.depth_format.get()]
+            .into_iter()
+            .flatten()
+            .collect();
+        let sample_count = gpu.supported_sample_count(&formats, sample_count);
+        if sample_count == self.sample_count.get() {
+            return;
+        }
+        tracing::info!(sample_count, "changing sample count");
+        self.sample_count.set(sample_count);
+        self.recreate_attachments(gpu.device());
+        drop(gpu);
+        self.init_scene();
+    }
+
+    /// The format of the depth texture, or `None` if rendering without one.
+    pub fn depth_format(&self) -> Option<wgpu::TextureFormat> {
+        self.depth_format.get()
+    }
+
+    /// Change the format of the depth texture, or disable it with `None`.
+    ///
+    /// The format is left unchanged if the new one is not a depth or stencil
+    /// format, or is not supported by the device with the current sample
+    /// count.
+    pub fn set_depth_format(
+        &self,
+        depth_format: Option<wgpu::TextureFormat>,
+    ) -> Result<(), DepthFormatError> {
+        if let Some(format) = depth_format {
+            self.check_depth_format(format)?;
+        }
+        if depth_format == self.depth_format.get() {
+            return Ok(());
+        }
+        tracing::info!(?depth_format, "changing depth format");
+        self.depth_format.set(depth_format);
+        self.recreate_attachments(self.gpu.borrow().device());
+        self.init_scene();
+        Ok(())
+    }
+
+    fn check_depth_format(&self, format: wgpu::TextureFormat) -> Result<(), DepthFormatError> {
+        if !format.is_depth_stencil_format() {
+            return Err(DepthFormatError::NotDepthStencil(format));
+        }
+        let gpu = self.gpu.borrow();
+        let missing = format.required_features() - gpu.device().features();
+        if !missing.is_empty() {
+            return Err(DepthFormatError::MissingFeatures {
+                format,
+                features: missing,
+            });
+        }
+        let sample_count = self.sample_count.get();
+        if !gpu.supports_sample_count(&[format], sample_count) {
+            return Err(DepthFormatError::UnsupportedSampleCount {
+                format,
+                sample_count,
+            });
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "debug", skip(self), fields(view = self.id))]
+    pub fn resize(&self, width: u32, height: u32, scale_factor: f32) {
+        let _span = trace_recorder::span("resize");
+        let gpu = self.gpu.borrow();
+        self.scale_factor.set(scale_factor);
+        self.target.resize(gpu.device(), width, height);
+        self.recreate_attachments(gpu.device());
+        self.scene
+            .borrow_mut()
+            .resize(&gpu, width, height, scale_factor);
+    }
+
+    /// (Re-)create the multisampled and depth textures with the target's
+    /// current size.
+    fn recreate_attachments(&self, device: &wgpu::Device) {
+        let (width, height) = self.target.size();
+        let sample_count = self.sample_count.get();
+        let create_view = |label, format| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size: wgpu::Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+        *self.attachments.borrow_mut() = Attachments {
+            msaa_view: (sample_count > 1)
+                .then(|| create_view("Multisampled Texture", self.target.format())),
+            depth_view: self
+                .depth_format
+                .get()
+                .map(|format| create_view("Depth Texture", format)),
+        };
+    }
+
+    #[tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(
+            view = self.id,
+            width = self.target.size().0,
+            height = self.target.size().1,
+            present_mode = ?self.target.present_mode(),
+            frame = self.frame_index.get(),
+        ),
+    )]
+    pub fn redraw(&self) {
+        let _span = trace_recorder::span("redraw");
+        if self.gpu.borrow().is_lost() && !self.recreate() {
+            return;
+        }
+
+        let gpu = self.gpu.borrow();
+        let mut scene = self.scene.borrow_mut();
+        gpu.reload_shader_if_changed();
+        let now = Instant::now();
+        let dt = self
+            .last_update
+            .replace(Some(now))
+            .map_or(Duration::ZERO, |last_update| now - last_update);
+        scene.update(&gpu, dt);
+        let globals_binding = self.globals_binding.borrow();
+        self.write_globals(&gpu, &globals_binding.buffer, now, dt);
+        let next_frame = {
+            let _span = trace_recorder::span("acquire");
+            let _entered = tracing::debug_span!("acquire").entered();
+            self.target.next_frame(gpu.device())
+        };
+        let frame = match next_frame {
+            Ok(frame) => frame,
+            Err(AcquireError::Skipped) => return,
+            Err(AcquireError::DeviceRecoveryNeeded) => {
+                self.recover_device();
+                return;
+            }
+        };
+        let encode_span = (
+            trace_recorder::span("encode"),
+            tracing::debug_span!("encode").entered(),
+        );
+        let mut encoder = gpu
+            .device()
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        // Skip measuring this frame if the last measurement is still pending.
+        let gpu_timer = self.gpu_timer.borrow();
+        let gpu_timer = gpu_timer.as_ref().filter(|timer| timer.is_ready());
+        let mut clear_color = self.clear_color.get();
+        if self.target.premultiplied_alpha() {
+            clear_color.r *= clear_color.a;
+            clear_color.g *= clear_color.a;
+            clear_color.b *= clear_color.a;
+        }
+        {
+            // When multisampling, render into the multisampled texture, and
+            // resolve that into the frame.
+            let attachments = self.attachments.borrow();
+            let color_attachment = match &attachments.msaa_view {
+                Some(msaa_view) => wgpu::RenderPassColorAttachment {
+                    view: msaa_view,
+                    depth_slice: None,
+                    resolve_target: Some(frame.view()),
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(clear_color),
+                        // Only the resolved result is needed.
+                        store: wgpu::StoreOp::Discard,
+                    },
+                },
+                None => wgpu::RenderPassColorAttachment {
+                    view: frame.view(),
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(clear_color),
+                        store: wgpu::StoreOp::Store,
+                    },
+                },
+            };
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(color_attachment)],
+                depth_stencil_attachment: attachments.depth_view.as_ref().map(|view| {
+                    let format = view.texture().format();
+                    wgpu::RenderPassDepthStencilAttachment {
+                        view,
+                        depth_ops: format.has_depth_aspect().then_some(wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(1.0),
+                            store: wgpu::StoreOp::Discard,
+                        }),
+                        stencil_ops: format.has_stencil_aspect().then_some(wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(0),
+                            store: wgpu::StoreOp::Discard,
+                        }),
+                    }
+                }),
+                timestamp_writes: gpu_timer.map(GpuTimer::timestamp_writes),
+                occlusion_query_set: None,
+                multiview_mask: None,
+            });
+            rpass.set_bind_group(0, &globals_binding.bind_group, &[]);
+            scene.render(&mut rpass);
+        }
+
+        if let Some(gpu_timer) = gpu_timer {
+            gpu_timer.resolve(&mut encoder);
+        }
+        let command_buffer = encoder.finish();
+        drop(encode_span);
+
+        {
+            let _span = trace_recorder::span("submit");
+            let _entered = tracing::debug_span!("submit").entered();
+            gpu.queue().submit(Some(command_buffer));
+        }
+        {
+            let _span = trace_recorder::span("present");
+            let _entered = tracing::debug_span!("present").entered();
+            frame.present();
+        }
+        self.frame_index.set(self.frame_index.get() + 1);
+        if let Some(gpu_timer) = gpu_timer {
+            gpu_timer.read_back(self.frame_counter.gpu_time_recorder());
+        }
+
+        self.frame_counter.update();
+    }
+
+    /// Update the globals for the frame that is about to be rendered.
+    fn write_globals(&self, gpu: &GpuContext, buffer: &wgpu::Buffer, now: Instant, dt: Duration) {
+        let (width, height) = self.target.size();
+        let scale_factor = self.scale_factor.get();
+        let globals = Globals {
+            time: (now - self.created).as_secs_f32(),
+            delta_time: dt.as_secs_f32(),
+            // Wraps around after more than a year at 120 Hz.
+            frame: self.frame_index.get() as u32,
+            scale_factor,
+            resolution: [width as f32, height as f32],
+            logical_resolution: [width as f32 / scale_factor, height as f32 / scale_factor],
+            pointer: self.pointer.get().unwrap_or(Globals::NO_POINTER),
+        };
+        gpu.queue()
+            .write_buffer(buffer, 0, bytemuck::bytes_of(&globals));
+        self.globals.set(globals);
+    }
+
+    /// Try to free up memory on the device after running out of it.
+    fn recover_device(&self) {
+        let gpu = self.gpu.borrow();
+        // Wait for in-flight work to finish, so that the resources used by it
+        // can be released, and then reallocate the target's textures.
+        if let Err(err) = gpu.device().poll(wgpu::PollType::wait_indefinitely()) {
+            tracing::error!(%err, "failed waiting for the device while recovering");
+        }
+        let (width, height) = self.target.size();
+        self.target.resize(gpu.device(), width, height);
+        self.recreate_attachments(gpu.device());
+        tracing::info!(
+            width,
+            height,
+            "recovered device, will retry on the next frame"
+        );
+    }
+
+    /// Recreate everything after the device was lost.
+    ///
+    /// Returns `false` if the device could not be recreated yet.
+    fn recreate(&self) -> bool {
+        let Some(gpu) = self.gpu.borrow().recreate() else {
+            return false;
+        };
+
+        // Reconfigure the surface, or recreate the texture, for the new device.
+        let (width, height) = self.target.size();
+        self.target.resize(gpu.device(), width, height);
+        *self.gpu_timer.borrow_mut() = GpuTimer::new(gpu.device(), gpu.queue());
+        *self.globals_binding.borrow_mut() = GlobalsBinding::new(&gpu);
+        *self.gpu.borrow_mut() = gpu;
+        self.recreate_attachments(self.gpu.borrow().device());
+        self.init_scene();
+
+        tracing::info!(width, height, "recreated renderer after device loss");
+        true
+    }
+
+    /// The GPU context currently in use.
+    pub fn gpu(&self) -> Arc<GpuContext> {
+        Arc::clone(&self.gpu.borrow())
+    }
+
+    /// Identifies the renderer in the `view` field of its tracing spans.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn target(&self) -> &T {
+        &self.target
+    }
+}
@@ -1,12 +1,15 @@
 use std::cell::Cell;
 
 use objc2::MainThreadMarker;
-use objc2_core_foundation::{kCFRunLoopDefaultMode, CFRunLoop};
+use objc2_core_foundation::{kCFRunLoopCommonModes, kCFRunLoopDefaultMode, CFRunLoop};
+
+use crate::trace_recorder;
 
 pub fn queue_closure(closure: impl FnOnce() + 'static) {
     // Convert `FnOnce()` to `Block<dyn Fn()>`.
     let closure = Cell::new(Some(closure));
     let block = block2::RcBlock::new(move || {
+        let _span = trace_recorder::span("queue_closure");
         if let Some(closure) = closure.take() {
             closure()
         } else {
@@ -24,3 +27,30 @@ pub fn queue_closure(closure: impl FnOnce() + 'static) {
     // will be run on the same thread.
     unsafe { run_loop.perform_block(mode.map(|mode| &**mode), Some(&block)) }
 }
+
+/// Run a closure on the main thread, from any thread.
+pub fn run_on_main(closure: impl FnOnce(MainThreadMarker) + Send + 'static) {
+    let closure = Cell::new(Some(closure));
+    let block = block2::RcBlock::new(move || {
+        // SAFETY: `CFRunLoopPerformBlock` runs the block on the thread of the
+        // run loop, which is the main thread.
+        let mtm = unsafe { MainThreadMarker::new_unchecked() };
+        if let Some(closure) = closure.take() {
+            closure(mtm)
+        } else {
+            tracing::error!("tried to execute closure on main thread twice");
+        }
+    });
+
+    let run_loop = CFRunLoop::main().unwrap();
+
+    // Use the common modes, to also run the closure while e.g. resizing.
+    let mode = unsafe { kCFRunLoopCommonModes };
+    // SAFETY: The runloop is valid, and the block is `'static`.
+    //
+    // The closure is `Send`, so it is fine to send it to the main thread to
+    // be executed there.
+    unsafe { run_loop.perform_block(mode.map(|mode| &**mode), Some(&block)) };
+    // Make sure that the main thread notices the block if it is sleeping.
+    run_loop.wake_up();
+}
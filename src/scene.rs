@@ -0,0 +1,84 @@
+//! The content that a [`Renderer`] draws.
+//!
+//! [`Renderer`]: crate::renderer::Renderer
+use std::fmt;
+use std::time::Duration;
+
+use crate::gpu_context::GpuContext;
+
+/// The formats of the textures that a scene renders into, which its render
+/// pipelines must be created for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TargetFormat {
+    /// The format of the color attachment.
+    pub format: wgpu::TextureFormat,
+    /// The number of samples per pixel, of both the color and the depth
+    /// attachment.
+    pub sample_count: u32,
+    /// The format of the depth attachment, `None` if there is none.
+    pub depth_format: Option<wgpu::TextureFormat>,
+}
+
+impl TargetFormat {
+    /// The depth/stencil state for render pipelines, which tests depth with
+    /// `Less` if the depth format has a depth aspect.
+    pub fn depth_stencil(&self) -> Option<wgpu::DepthStencilState> {
+        self.depth_format.map(|format| wgpu::DepthStencilState {
+            format,
+            // Stencil-only formats have no depth to test against.
+            depth_write_enabled: format.has_depth_aspect(),
+            depth_compare: if format.has_depth_aspect() {
+                wgpu::CompareFunction::Less
+            } else {
+                wgpu::CompareFunction::Always
+            },
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        })
+    }
+
+    /// The multisample state for render pipelines.
+    pub fn multisample(&self) -> wgpu::MultisampleState {
+        wgpu::MultisampleState {
+            count: self.sample_count,
+            ..Default::default()
+        }
+    }
+}
+
+/// Something that can be drawn by a [`Renderer`].
+///
+/// The renderer takes care of the target, the attachments and the frame
+/// statistics, and calls into the scene to draw the content of each frame.
+///
+/// [`Renderer`]: crate::renderer::Renderer
+pub trait Scene: fmt::Debug {
+    /// Create the GPU resources of the scene.
+    ///
+    /// This is called again whenever the device is recreated, or the target
+    /// format changes, and is always followed by a call to
+    /// [`resize`](Scene::resize).
+    fn init(&mut self, gpu: &GpuContext, format: TargetFormat);
+
+    /// The target was resized to `width` by `height` pixels.
+    fn resize(&mut self, gpu: &GpuContext, width: u32, height: u32, scale_factor: f32);
+
+    /// Advance the scene by `dt`, the time since the last frame, before the
+    /// frame is rendered.
+    ///
+    /// `dt` is zero for the first frame.
+    fn update(&mut self, gpu: &GpuContext, dt: Duration);
+
+    /// Record the draw commands of a frame.
+    ///
+    /// The render pass was created for the format passed to the last
+    /// [`init`](Scene::init), and has already been cleared.
+    ///
+    /// The [`Globals`] of the frame are bound to group 0, so the pipelines
+    /// must use [`GpuContext::globals_bind_group_layout`] as their first bind
+    /// group layout, and be created from shaders compiled with
+    /// [`GpuContext::create_shader_module`].
+    ///
+    /// [`Globals`]: crate::globals::Globals
+    fn render(&mut self, rpass: &mut wgpu::RenderPass<'_>);
+}
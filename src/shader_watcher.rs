@@ -0,0 +1,77 @@
+//! Watching the shader source for changes during development.
+use std::path::PathBuf;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// How often to check the file for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Watches a shader file on disk, by periodically reading it on a helper
+/// thread.
+#[derive(Debug)]
+pub struct ShaderWatcher {
+    path: PathBuf,
+    changed_source: Arc<Mutex<Option<String>>>,
+    stop: Arc<AtomicBool>,
+    watcher_thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for ShaderWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.watcher_thread
+            .take()
+            .expect("watcher thread set")
+            .join()
+            .expect("watcher thread");
+    }
+}
+
+impl ShaderWatcher {
+    /// Start watching `path`, which initially has the contents `source`.
+    pub fn new(path: PathBuf, source: String) -> Self {
+        let changed_source = Arc::new(Mutex::new(None));
+        let changed_source_clone = Arc::clone(&changed_source);
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_clone = Arc::clone(&stop);
+        let path_clone = path.clone();
+        let watcher_thread = thread::spawn(move || {
+            let mut last_source = source;
+            while !stop_clone.load(Ordering::Relaxed) {
+                thread::sleep(POLL_INTERVAL);
+                match std::fs::read_to_string(&path_clone) {
+                    Ok(source) if source != last_source => {
+                        tracing::info!(path = %path_clone.display(), "shader changed on disk");
+                        *changed_source_clone.lock().unwrap() = Some(source.clone());
+                        last_source = source;
+                    }
+                    Ok(_) => {}
+                    // May happen while the file is being saved.
+                    Err(err) => {
+                        tracing::debug!(path = %path_clone.display(), %err, "failed reading shader");
+                    }
+                }
+            }
+        });
+        Self {
+            path,
+            changed_source,
+            stop,
+            watcher_thread: Some(watcher_thread),
+        }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Take the new source of the shader, if it has changed since the last
+    /// time this was called.
+    pub fn take_changed_source(&self) -> Option<String> {
+        self.changed_source.lock().unwrap().take()
+    }
+}
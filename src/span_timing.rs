@@ -0,0 +1,151 @@
+//! A `tracing` layer that summarizes how long spans take.
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tracing::span;
+use tracing::Subscriber;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// How often [`SpanTimingLayer::new`] logs the summaries.
+pub const DEFAULT_SUMMARY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The durations of all the closed spans with the same name, since the last
+/// summary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpanSummary {
+    pub name: &'static str,
+    pub count: u32,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+impl SpanSummary {
+    pub fn avg(&self) -> Duration {
+        self.total / self.count.max(1)
+    }
+}
+
+/// Aggregates the time spent inside each span (excluding time where the
+/// span was not entered), and periodically reports a summary per span name.
+pub struct SpanTimingLayer {
+    interval: Duration,
+    state: Mutex<State>,
+    report: Reporter,
+}
+
+type Reporter = Box<dyn Fn(&[SpanSummary]) + Send + Sync>;
+
+struct State {
+    last_report: Instant,
+    summaries: BTreeMap<&'static str, SpanSummary>,
+}
+
+/// Stored in the extensions of each span.
+struct Timing {
+    busy: Duration,
+    entered_at: Option<Instant>,
+}
+
+impl SpanTimingLayer {
+    /// Log the summaries with `tracing` every [`DEFAULT_SUMMARY_INTERVAL`].
+    pub fn new() -> Self {
+        Self::with_reporter(DEFAULT_SUMMARY_INTERVAL, |summaries| {
+            for summary in summaries {
+                tracing::info!(
+                    span = summary.name,
+                    count = summary.count,
+                    avg_ms = summary.avg().as_secs_f32() * 1000.0,
+                    max_ms = summary.max.as_secs_f32() * 1000.0,
+                    total_ms = summary.total.as_secs_f32() * 1000.0,
+                    "span timings"
+                );
+            }
+        })
+    }
+
+    /// Pass the summaries to `report` whenever a span closes, and at least
+    /// `interval` has passed since the last report.
+    pub fn with_reporter(
+        interval: Duration,
+        report: impl Fn(&[SpanSummary]) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            interval,
+            state: Mutex::new(State {
+                last_report: Instant::now(),
+                summaries: BTreeMap::new(),
+            }),
+            report: Box::new(report),
+        }
+    }
+}
+
+impl Default for SpanTimingLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for SpanTimingLayer {
+    fn on_new_span(&self, _attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let span = ctx.span(id).expect("span exists");
+        span.extensions_mut().insert(Timing {
+            busy: Duration::ZERO,
+            entered_at: None,
+        });
+    }
+
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+        let span = ctx.span(id).expect("span exists");
+        let mut extensions = span.extensions_mut();
+        if let Some(timing) = extensions.get_mut::<Timing>() {
+            timing.entered_at = Some(Instant::now());
+        }
+    }
+
+    fn on_exit(&self, id: &span::Id, ctx: Context<'_, S>) {
+        let span = ctx.span(id).expect("span exists");
+        let mut extensions = span.extensions_mut();
+        if let Some(timing) = extensions.get_mut::<Timing>() {
+            if let Some(entered_at) = timing.entered_at.take() {
+                timing.busy += entered_at.elapsed();
+            }
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        let span = ctx.span(&id).expect("span exists");
+        let extensions = span.extensions();
+        let Some(busy) = extensions.get::<Timing>().map(|timing| timing.busy) else {
+            return;
+        };
+        drop(extensions);
+        let name = span.metadata().name();
+
+        let report = {
+            let mut state = self.state.lock().unwrap();
+            let summary = state.summaries.entry(name).or_insert(SpanSummary {
+                name,
+                count: 0,
+                total: Duration::ZERO,
+                max: Duration::ZERO,
+            });
+            summary.count += 1;
+            summary.total += busy;
+            summary.max = summary.max.max(busy);
+
+            if state.last_report.elapsed() < self.interval {
+                return;
+            }
+            state.last_report = Instant::now();
+            std::mem::take(&mut state.summaries)
+        };
+        // Reported without holding the lock, since the reporter may emit
+        // events, which may create spans of their own.
+        let report: Vec<_> = report.into_values().collect();
+        (self.report)(&report);
+    }
+}
@@ -0,0 +1,194 @@
+//! Where the statistics collected by [`FrameCounter`] are reported to.
+//!
+//! [`FrameCounter`]: crate::frame_counter::FrameCounter
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::frame_counter::FrameTimeStats;
+
+/// The statistics over the frames rendered since the last report.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameStats {
+    /// The time since the last report.
+    pub elapsed: Duration,
+    pub frame_count: u32,
+    pub fps: f32,
+    /// The maximum frame latency of the surface, if rendering to one.
+    pub frame_latency: Option<u32>,
+    /// The average GPU time per frame, if timestamp queries are supported.
+    pub gpu_ms: Option<f32>,
+    /// `None` if fewer than two frames were rendered.
+    pub frame_times: Option<FrameTimeStats>,
+}
+
+/// Receives the statistics collected by a [`FrameCounter`], about once per
+/// second.
+///
+/// Reports are made from a helper thread, so that the thread that renders
+/// never waits for a sink, which may thus block, e.g. on file I/O.
+///
+/// [`FrameCounter`]: crate::frame_counter::FrameCounter
+pub trait StatsSink: fmt::Debug + Send {
+    fn report(&mut self, stats: &FrameStats);
+}
+
+/// Log the statistics with `tracing`.
+#[derive(Debug, Default)]
+pub struct TracingSink;
+
+impl StatsSink for TracingSink {
+    fn report(&mut self, stats: &FrameStats) {
+        let ms = |f: fn(&FrameTimeStats) -> Duration| {
+            stats
+                .frame_times
+                .as_ref()
+                .map(|frame_times| f(frame_times).as_secs_f32() * 1000.0)
+        };
+        tracing::info!(
+            frame_latency = stats.frame_latency,
+            gpu_ms = stats.gpu_ms,
+            min_ms = ms(|stats| stats.min),
+            avg_ms = ms(|stats| stats.avg),
+            p50_ms = ms(|stats| stats.p50),
+            p95_ms = ms(|stats| stats.p95),
+            p99_ms = ms(|stats| stats.p99),
+            max_ms = ms(|stats| stats.max),
+            hitches = stats.frame_times.as_ref().map(|stats| stats.hitches),
+            "FPS: {:.1}",
+            stats.fps
+        );
+    }
+}
+
+/// The columns written by [`CsvSink`], and the keys written by
+/// [`JsonLinesSink`].
+const COLUMNS: &[&str] = &[
+    "elapsed_ms",
+    "frame_count",
+    "fps",
+    "frame_latency",
+    "gpu_ms",
+    "min_ms",
+    "avg_ms",
+    "p50_ms",
+    "p95_ms",
+    "p99_ms",
+    "max_ms",
+    "hitches",
+];
+
+/// The values for each of [`COLUMNS`], or `None` if unknown.
+fn values(stats: &FrameStats) -> [Option<String>; 12] {
+    fn ms(duration: Duration) -> f64 {
+        duration.as_secs_f64() * 1000.0
+    }
+    let frame_times = |f: fn(&FrameTimeStats) -> String| stats.frame_times.as_ref().map(f);
+    [
+        Some(ms(stats.elapsed).to_string()),
+        Some(stats.frame_count.to_string()),
+        Some(stats.fps.to_string()),
+        stats.frame_latency.map(|latency| latency.to_string()),
+        stats.gpu_ms.map(|gpu_ms| gpu_ms.to_string()),
+        frame_times(|stats| ms(stats.min).to_string()),
+        frame_times(|stats| ms(stats.avg).to_string()),
+        frame_times(|stats| ms(stats.p50).to_string()),
+        frame_times(|stats| ms(stats.p95).to_string()),
+        frame_times(|stats| ms(stats.p99).to_string()),
+        frame_times(|stats| ms(stats.max).to_string()),
+        frame_times(|stats| stats.hitches.to_string()),
+    ]
+}
+
+/// Write the statistics to a CSV file, with one row per report.
+///
+/// Unknown values are left empty.
+#[derive(Debug)]
+pub struct CsvSink {
+    writer: BufWriter<File>,
+}
+
+impl CsvSink {
+    /// Create (or truncate) the file at `path`, and write the header.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "{}", COLUMNS.join(","))?;
+        writer.flush()?;
+        Ok(Self { writer })
+    }
+}
+
+impl StatsSink for CsvSink {
+    fn report(&mut self, stats: &FrameStats) {
+        let row: Vec<String> = values(stats)
+            .into_iter()
+            .map(Option::unwrap_or_default)
+            .collect();
+        // Flush every row, so that the file is complete if the process exits.
+        let result = writeln!(self.writer, "{}", row.join(",")).and_then(|_| self.writer.flush());
+        if let Err(err) = result {
+            tracing::warn!(%err, "failed writing stats to CSV");
+        }
+    }
+}
+
+/// Write the statistics to a file, with one JSON object per line.
+///
+/// Unknown values are written as `null`.
+#[derive(Debug)]
+pub struct JsonLinesSink {
+    writer: BufWriter<File>,
+}
+
+impl JsonLinesSink {
+    /// Create (or truncate) the file at `path`.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+        })
+    }
+}
+
+impl StatsSink for JsonLinesSink {
+    fn report(&mut self, stats: &FrameStats) {
+        // All values are numbers, so they don't need escaping.
+        let fields: Vec<String> = COLUMNS
+            .iter()
+            .zip(values(stats))
+            .map(|(key, value)| format!("\"{key}\":{}", value.as_deref().unwrap_or("null")))
+            .collect();
+        let result =
+            writeln!(self.writer, "{{{}}}", fields.join(",")).and_then(|_| self.writer.flush());
+        if let Err(err) = result {
+            tracing::warn!(%err, "failed writing stats to JSON lines");
+        }
+    }
+}
+
+/// Collect the statistics in memory, for example to inspect them in tests.
+///
+/// Clones share the same reports.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySink {
+    reports: Arc<Mutex<Vec<FrameStats>>>,
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The reports received so far.
+    pub fn reports(&self) -> Vec<FrameStats> {
+        self.reports.lock().unwrap().clone()
+    }
+}
+
+impl StatsSink for MemorySink {
+    fn report(&mut self, stats: &FrameStats) {
+        self.reports.lock().unwrap().push(*stats);
+    }
+}
@@ -0,0 +1,88 @@
+//! Choosing which format to configure a surface with.
+//!
+//! The formats supported by a surface are reported in no particular order,
+//! so just taking the first one leads to colors looking different between
+//! machines, depending on whether it happens to be an sRGB format or not.
+
+/// Which kind of format to prefer for a surface.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FormatPreference {
+    /// An sRGB format, so that blending and the shader's output are
+    /// interpreted as linear colors.
+    #[default]
+    Srgb,
+    /// `Rgba16Float`, which allows colors outside of the `0.0..=1.0` range
+    /// to be output on displays that support it.
+    ///
+    /// Falls back to [`FormatPreference::Srgb`] if not supported.
+    ExtendedRange,
+}
+
+/// The format chosen for a surface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SurfaceFormat {
+    /// The format to configure the surface with.
+    pub format: wgpu::TextureFormat,
+    /// The format to render through, which differs from `format` if the
+    /// surface only supports linear formats, in which case we render through
+    /// an sRGB view of it.
+    pub view_format: wgpu::TextureFormat,
+    /// Why this format was chosen.
+    pub reason: &'static str,
+}
+
+impl SurfaceFormat {
+    /// Choose between the formats supported by a surface.
+    ///
+    /// Returns `None` if `formats` is empty.
+    pub fn choose(formats: &[wgpu::TextureFormat], preference: FormatPreference) -> Option<Self> {
+        let extended_range = wgpu::TextureFormat::Rgba16Float;
+        match preference {
+            FormatPreference::ExtendedRange if formats.contains(&extended_range) => {
+                return Some(Self::new(extended_range, "extended range requested"));
+            }
+            FormatPreference::ExtendedRange => {
+                let mut format = Self::choose(formats, FormatPreference::Srgb)?;
+                format.reason = "extended range requested, but not supported by the surface";
+                return Some(format);
+            }
+            FormatPreference::Srgb => {}
+        }
+
+        if let Some(&format) = formats.iter().find(|format| format.is_srgb()) {
+            return Some(Self::new(format, "first sRGB format"));
+        }
+        if let Some(&format) = formats
+            .iter()
+            .find(|format| format.add_srgb_suffix() != **format)
+        {
+            return Some(Self {
+                format,
+                view_format: format.add_srgb_suffix(),
+                reason: "no sRGB format, rendering through an sRGB view",
+            });
+        }
+        let &format = formats.first()?;
+        Some(Self::new(
+            format,
+            "no sRGB format, nor one with an sRGB view",
+        ))
+    }
+
+    fn new(format: wgpu::TextureFormat, reason: &'static str) -> Self {
+        Self {
+            format,
+            view_format: format,
+            reason,
+        }
+    }
+
+    /// The formats that must be allowed for views of the surface's textures.
+    pub fn view_formats(&self) -> Vec<wgpu::TextureFormat> {
+        if self.view_format == self.format {
+            vec![]
+        } else {
+            vec![self.view_format]
+        }
+    }
+}
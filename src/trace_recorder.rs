@@ -0,0 +1,201 @@
+//! Recording when each stage of a frame happens, for viewing in Perfetto
+//! (<https://ui.perfetto.dev>) or `chrome://tracing`.
+//!
+//! Recording is opt-in with [`start`]. Until then, [`span`] does nothing.
+//!
+//! The events are written in the Chrome trace-event JSON array format as
+//! they are recorded, and the file is flushed about once per second. The
+//! array is only terminated by [`finish`], but the viewers also accept files
+//! where the terminator is missing, in case the process exits abruptly.
+use std::cell::Cell;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// How often to flush the recorded events to the file.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+static RECORDER: OnceLock<Recorder> = OnceLock::new();
+
+#[derive(Debug)]
+struct Recorder {
+    start: Instant,
+    // `None` once finished.
+    writer: Mutex<Option<TraceWriter>>,
+}
+
+#[derive(Debug)]
+struct TraceWriter {
+    writer: BufWriter<File>,
+    last_flush: Instant,
+}
+
+/// Start recording into the file at `path`, which is created or truncated.
+///
+/// Recording can only be started once per process.
+pub fn start(path: impl AsRef<Path>) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path.as_ref())?);
+    writeln!(writer, "[")?;
+    let recorder = Recorder {
+        start: Instant::now(),
+        writer: Mutex::new(Some(TraceWriter {
+            writer,
+            last_flush: Instant::now(),
+        })),
+    };
+    RECORDER
+        .set(recorder)
+        .map_err(|_| io::Error::other("trace recording already started"))?;
+    tracing::info!(path = %path.as_ref().display(), "recording trace");
+    Ok(())
+}
+
+/// Stop recording, and terminate the file.
+///
+/// Does nothing if not recording.
+pub fn finish() -> io::Result<()> {
+    let Some(recorder) = RECORDER.get() else {
+        return Ok(());
+    };
+    let Some(mut trace_writer) = recorder.writer.lock().unwrap().take() else {
+        return Ok(());
+    };
+    // End with an event, since JSON doesn't allow trailing commas.
+    write_event(
+        &mut trace_writer.writer,
+        "trace_finished",
+        "i",
+        recorder.timestamp(Instant::now()),
+        None,
+    )?;
+    writeln!(trace_writer.writer, "\n]")?;
+    trace_writer.writer.flush()
+}
+
+/// Whether a trace is currently being recorded.
+pub fn is_recording() -> bool {
+    RECORDER
+        .get()
+        .is_some_and(|recorder| recorder.writer.lock().unwrap().is_some())
+}
+
+/// Record the time from now until the returned guard is dropped.
+///
+/// `name` is shown as the name of the slice in the viewer.
+#[must_use = "the span ends when the guard is dropped"]
+pub fn span(name: &'static str) -> Span {
+    Span {
+        name,
+        start: RECORDER.get().map(|_| Instant::now()),
+    }
+}
+
+/// Records a span when dropped, see [`span`].
+#[derive(Debug)]
+pub struct Span {
+    name: &'static str,
+    // `None` if not recording.
+    start: Option<Instant>,
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let (Some(recorder), Some(start)) = (RECORDER.get(), self.start) {
+            let duration = start.elapsed();
+            recorder.record(self.name, start, duration);
+        }
+    }
+}
+
+impl Recorder {
+    /// Microseconds since the recording started.
+    fn timestamp(&self, instant: Instant) -> f64 {
+        instant.saturating_duration_since(self.start).as_secs_f64() * 1e6
+    }
+
+    fn record(&self, name: &'static str, start: Instant, duration: Duration) {
+        let (tid, is_new_thread) = thread_id();
+        let ts = self.timestamp(start);
+        let mut writer = self.writer.lock().unwrap();
+        let Some(trace_writer) = &mut *writer else {
+            return;
+        };
+        let result = (|| {
+            if is_new_thread {
+                write_thread_name(&mut trace_writer.writer, tid)?;
+            }
+            write_event(
+                &mut trace_writer.writer,
+                name,
+                "X",
+                ts,
+                Some(duration.as_secs_f64() * 1e6),
+            )?;
+            writeln!(trace_writer.writer, ",")?;
+            if trace_writer.last_flush.elapsed() > FLUSH_INTERVAL {
+                trace_writer.writer.flush()?;
+                trace_writer.last_flush = Instant::now();
+            }
+            Ok::<_, io::Error>(())
+        })();
+        if let Err(err) = result {
+            tracing::error!(%err, "failed writing trace, stopping recording");
+            *writer = None;
+        }
+    }
+}
+
+/// A small, stable identifier for the current thread, and whether this is
+/// the first time it was requested on this thread.
+fn thread_id() -> (u64, bool) {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+    thread_local! {
+        static ID: Cell<Option<u64>> = const { Cell::new(None) };
+    }
+    ID.with(|id| match id.get() {
+        Some(id) => (id, false),
+        None => {
+            let new_id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+            id.set(Some(new_id));
+            (new_id, true)
+        }
+    })
+}
+
+fn write_event(
+    writer: &mut impl Write,
+    name: &str,
+    phase: &str,
+    ts: f64,
+    dur: Option<f64>,
+) -> io::Result<()> {
+    let (tid, _) = thread_id();
+    let pid = std::process::id();
+    write!(
+        writer,
+        r#"{{"name":"{name}","ph":"{phase}","ts":{ts:.3},"pid":{pid},"tid":{tid}"#
+    )?;
+    if let Some(dur) = dur {
+        write!(writer, r#","dur":{dur:.3}"#)?;
+    }
+    if phase == "i" {
+        // Show instant events across all threads.
+        write!(writer, r#","s":"g""#)?;
+    }
+    write!(writer, "}}")
+}
+
+/// Name the thread in the viewer.
+fn write_thread_name(writer: &mut impl Write, tid: u64) -> io::Result<()> {
+    let thread = std::thread::current();
+    let name = thread.name().unwrap_or("unnamed");
+    let name = name.replace('\\', "\\\\").replace('"', "\\\"");
+    let pid = std::process::id();
+    writeln!(
+        writer,
+        r#"{{"name":"thread_name","ph":"M","pid":{pid},"tid":{tid},"args":{{"name":"{name}"}}}},"#
+    )
+}
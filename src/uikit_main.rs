@@ -1,4 +1,5 @@
 use std::cell::OnceCell;
+use std::sync::OnceLock;
 
 use objc2::rc::{Allocated, Retained};
 use objc2::{define_class, msg_send, ClassType, DefinedClass, MainThreadMarker, MainThreadOnly};
@@ -9,8 +10,14 @@ use objc2_ui_kit::{
     UIViewController, UIWindow,
 };
 
+use crate::config::Config;
+use crate::trace_recorder;
 use crate::view::WgpuTriangleView;
 
+// `UIApplicationMain` creates the delegate itself, so we have to pass the
+// configuration to it through a static.
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
 define_class!(
     // SAFETY:
     // - The superclass UIViewController does not have any subclassing requirements.
@@ -66,6 +73,14 @@ define_class!(
             tracing::info!("applicationDidFinishLaunching:");
             self.setup();
         }
+
+        #[unsafe(method(applicationWillTerminate:))]
+        fn will_terminate(&self, _application: &UIApplication) {
+            tracing::info!("applicationWillTerminate:");
+            if let Err(err) = trace_recorder::finish() {
+                tracing::error!(%err, "failed finishing trace");
+            }
+        }
     }
 );
 
@@ -86,23 +101,35 @@ impl Delegate {
 
         let view_controller = ViewController::new(mtm);
 
-        if cfg!(feature = "two-triangles") {
+        let config = *CONFIG.get().expect("config set in `main`");
+        let views = if config.two_triangles {
             // Frame will be resized by NSStackView automatically
             let frame = CGRect::new(CGPoint::new(0.0, 0.0), CGSize::new(1.0, 1.0));
+            let views = vec![
+                WgpuTriangleView::new(mtm, frame, config),
+                WgpuTriangleView::new(mtm, frame, config),
+            ];
             let view = UIStackView::new(mtm);
-            view.addArrangedSubview(&WgpuTriangleView::new(mtm, frame));
-            view.addArrangedSubview(&WgpuTriangleView::new(mtm, frame));
+            for triangle_view in &views {
+                view.addArrangedSubview(triangle_view);
+            }
             // view.setOrientation(NSUserInterfaceLayoutOrientation::Horizontal);
             view.setDistribution(UIStackViewDistribution::FillEqually);
             view_controller.setView(Some(&view));
+            views
         } else {
-            view_controller.setView(Some(&WgpuTriangleView::new(mtm, frame)));
-        }
+            let view = WgpuTriangleView::new(mtm, frame, config);
+            view_controller.setView(Some(&view));
+            vec![view]
+        };
 
         window.setRootViewController(Some(&view_controller));
 
         window.makeKeyAndVisible();
 
+        // Show the window first, and set up the renderers afterwards.
+        WgpuTriangleView::install_renderers_in_background(views, config);
+
         self.ivars()
             .window
             .set(window)
@@ -110,6 +137,7 @@ impl Delegate {
     }
 }
 
-pub fn main(mtm: MainThreadMarker) {
+pub fn main(mtm: MainThreadMarker, config: Config) {
+    CONFIG.set(config).expect("can only run once");
     UIApplication::main(None, Some(&NSString::from_class(Delegate::class())), mtm)
 }
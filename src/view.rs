@@ -1,7 +1,11 @@
-use std::cell::OnceCell;
+use std::cell::{OnceCell, RefCell};
+use std::mem::ManuallyDrop;
 use std::ptr::NonNull;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use objc2::rc::Retained;
+use objc2::rc::{Retained, Weak};
 use objc2::{define_class, msg_send, sel, DeclaredClass, MainThreadMarker, Message};
 use objc2_core_foundation::{CGRect, CGSize};
 use objc2_foundation::{NSObjectProtocol, NSRunLoop, NSRunLoopCommonModes};
@@ -11,7 +15,12 @@ use wgpu::rwh::{
     RawWindowHandle, UiKitWindowHandle, WindowHandle,
 };
 
-use crate::run_loop::queue_closure;
+use crate::config::Config;
+use crate::gpu_context::GpuContext;
+use crate::render_target::WindowSurface;
+use crate::renderer::Renderer;
+use crate::run_loop::{queue_closure, run_on_main};
+use crate::trace_recorder;
 use crate::wgpu_triangle::Triangle;
 
 #[cfg(feature = "mtkview")]
@@ -21,13 +30,19 @@ type View = objc2_app_kit::NSView;
 #[cfg(all(not(target_os = "macos"), not(feature = "mtkview")))]
 type View = objc2_ui_kit::UIView;
 
+#[derive(Debug)]
+pub struct Ivars {
+    config: Config,
+    renderer: OnceCell<Renderer<WindowSurface<'static>>>,
+}
+
 define_class!(
     // SAFETY:
     // - The superclass View does not have any subclassing requirements.
     // - `Delegate` does not implement `Drop`.
     #[unsafe(super(View))]
     #[name = "View"]
-    #[ivars = OnceCell<Triangle<'static>>]
+    #[ivars = Ivars]
     pub struct WgpuTriangleView;
 
     unsafe impl NSObjectProtocol for WgpuTriangleView {}
@@ -37,16 +52,19 @@ define_class!(
     impl WgpuTriangleView {
         #[unsafe(method(wantsUpdateLayer))]
         fn wants_update_layer(&self) -> bool {
-            cfg!(not(feature = "draw-rect"))
+            !self.ivars().config.draw_rect
         }
 
         #[unsafe(method(updateLayer))]
         fn update_layer(&self) {
+            let _span = trace_recorder::span("updateLayer");
             tracing::trace!(live_resize = self.inLiveResize(), "triggered `updateLayer`");
-            let triangle = self.ivars().get().expect("initialized");
-            triangle.redraw();
+            let Some(renderer) = self.renderer() else {
+                return;
+            };
+            renderer.redraw();
 
-            if cfg!(feature = "queue-display") {
+            if self.ivars().config.queue_display {
                 let view = self.retain();
                 queue_closure(move || view.setNeedsDisplay(true));
             }
@@ -54,11 +72,14 @@ define_class!(
 
         #[unsafe(method(drawRect:))]
         fn draw_rect(&self, _rect: CGRect) {
+            let _span = trace_recorder::span("drawRect:");
             tracing::trace!(live_resize = self.inLiveResize(), "triggered `drawRect:`");
-            let triangle = self.ivars().get().expect("initialized");
-            triangle.redraw();
+            let Some(renderer) = self.renderer() else {
+                return;
+            };
+            renderer.redraw();
 
-            if cfg!(feature = "queue-display") {
+            if self.ivars().config.queue_display {
                 let view = self.retain();
                 queue_closure(move || view.setNeedsDisplay(true));
             }
@@ -74,17 +95,16 @@ define_class!(
                 ?new_size,
                 "triggered `frameDidChange:`"
             );
-            let triangle = self.ivars().get().expect("initialized");
-            triangle.resize(
+            let Some(renderer) = self.renderer() else {
+                return;
+            };
+            renderer.resize(
                 new_size.width as u32,
                 new_size.height as u32,
                 self.window().unwrap().backingScaleFactor() as f32,
             );
-            if cfg!(all(
-                feature = "immediate-redraw",
-                not(feature = "display-link")
-            )) {
-                triangle.redraw();
+            if self.ivars().config.redraw_on_resize() {
+                renderer.redraw();
             }
         }
 
@@ -96,19 +116,34 @@ define_class!(
                 ?new_size,
                 "triggered `viewDidChangeBackingProperties`"
             );
-            let triangle = self.ivars().get().expect("initialized");
-            triangle.resize(
+            let Some(renderer) = self.renderer() else {
+                return;
+            };
+            renderer.resize(
                 new_size.width as u32,
                 new_size.height as u32,
                 self.window().unwrap().backingScaleFactor() as f32,
             );
-            if cfg!(all(
-                feature = "immediate-redraw",
-                not(feature = "display-link")
-            )) {
-                triangle.redraw();
+            if self.ivars().config.redraw_on_resize() {
+                renderer.redraw();
             }
         }
+
+        #[unsafe(method(mouseMoved:))]
+        fn mouse_moved(&self, event: &objc2_app_kit::NSEvent) {
+            self.set_pointer_position(Some(event));
+        }
+
+        // Tracked while a button is pressed, even outside of the view.
+        #[unsafe(method(mouseDragged:))]
+        fn mouse_dragged(&self, event: &objc2_app_kit::NSEvent) {
+            self.set_pointer_position(Some(event));
+        }
+
+        #[unsafe(method(mouseExited:))]
+        fn mouse_exited(&self, _event: &objc2_app_kit::NSEvent) {
+            self.set_pointer_position(None);
+        }
     }
 
     /// UIView
@@ -116,11 +151,14 @@ define_class!(
     impl WgpuTriangleView {
         #[unsafe(method(drawRect:))]
         fn draw_rect(&self, _rect: CGRect) {
+            let _span = trace_recorder::span("drawRect:");
             tracing::trace!("triggered `drawRect:`");
-            let triangle = self.ivars().get().expect("initialized");
-            triangle.redraw();
+            let Some(renderer) = self.renderer() else {
+                return;
+            };
+            renderer.redraw();
 
-            if cfg!(feature = "queue-display") {
+            if self.ivars().config.queue_display {
                 let view = self.retain();
                 queue_closure(move || view.setNeedsDisplay());
             }
@@ -134,33 +172,69 @@ define_class!(
         fn layout_subviews(&self) {
             let new_size = scaled_view_frame(self);
             tracing::debug!("triggered `layoutSubviews`, new_size: {:?}", new_size);
-            let triangle = self.ivars().get().expect("initialized");
-            triangle.resize(
-                new_size.width as u32,
-                new_size.height as u32,
-                self.contentScaleFactor() as f32,
-            );
-            if cfg!(all(
-                feature = "immediate-redraw",
-                not(feature = "display-link")
-            )) {
-                triangle.redraw();
+            if let Some(renderer) = self.renderer() {
+                renderer.resize(
+                    new_size.width as u32,
+                    new_size.height as u32,
+                    self.contentScaleFactor() as f32,
+                );
+                if self.ivars().config.redraw_on_resize() {
+                    renderer.redraw();
+                }
             }
 
             // Calling super here is not really necessary, as we have no
             // subviews, but we do it anyway just to make sure.
             let _: () = unsafe { objc2::msg_send![super(self), layoutSubviews] };
         }
+
+        #[unsafe(method(touchesBegan:withEvent:))]
+        fn touches_began(
+            &self,
+            touches: &objc2_foundation::NSSet<objc2_ui_kit::UITouch>,
+            _event: Option<&objc2_ui_kit::UIEvent>,
+        ) {
+            self.set_pointer_position(touches.anyObject().as_deref());
+        }
+
+        #[unsafe(method(touchesMoved:withEvent:))]
+        fn touches_moved(
+            &self,
+            touches: &objc2_foundation::NSSet<objc2_ui_kit::UITouch>,
+            _event: Option<&objc2_ui_kit::UIEvent>,
+        ) {
+            self.set_pointer_position(touches.anyObject().as_deref());
+        }
+
+        #[unsafe(method(touchesEnded:withEvent:))]
+        fn touches_ended(
+            &self,
+            _touches: &objc2_foundation::NSSet<objc2_ui_kit::UITouch>,
+            _event: Option<&objc2_ui_kit::UIEvent>,
+        ) {
+            self.set_pointer_position(None);
+        }
+
+        #[unsafe(method(touchesCancelled:withEvent:))]
+        fn touches_cancelled(
+            &self,
+            _touches: &objc2_foundation::NSSet<objc2_ui_kit::UITouch>,
+            _event: Option<&objc2_ui_kit::UIEvent>,
+        ) {
+            self.set_pointer_position(None);
+        }
     }
 
     /// For DisplayLink
     impl WgpuTriangleView {
         #[unsafe(method(step:))]
         fn step(&self, _sender: &CADisplayLink) {
+            let _span = trace_recorder::span("step:");
             tracing::trace!("triggered `step:`");
-            if cfg!(feature = "immediate-redraw") {
-                let triangle = self.ivars().get().expect("initialized");
-                triangle.redraw();
+            if self.ivars().config.immediate_redraw {
+                if let Some(renderer) = self.renderer() {
+                    renderer.redraw();
+                }
             } else {
                 #[cfg(target_os = "macos")]
                 self.setNeedsDisplay(true);
@@ -171,6 +245,12 @@ define_class!(
     }
 );
 
+thread_local! {
+    /// The views with a renderer installed, which are redrawn once a lost
+    /// device has been recreated. Only used on the main thread.
+    static VIEWS: RefCell<Vec<Weak<WgpuTriangleView>>> = const { RefCell::new(Vec::new()) };
+}
+
 // Helper for passing the view to `create_surface`.
 struct ViewWrapper(Retained<WgpuTriangleView>);
 
@@ -202,25 +282,35 @@ impl HasDisplayHandle for ViewWrapper {
     }
 }
 
+// Helper for sending the views to a background thread and back.
+//
+// The views are wrapped in `ManuallyDrop`, so that they are never released on
+// the background thread, even if it panics. They are leaked in that case.
+struct ViewsForMainThread(ManuallyDrop<Vec<Retained<WgpuTriangleView>>>);
+
+// SAFETY: The views are only accessed (and released) on the main thread, in
+// the closure passed to `run_on_main`.
+unsafe impl Send for ViewsForMainThread {}
+
+impl ViewsForMainThread {
+    fn into_inner(self, _mtm: MainThreadMarker) -> Vec<Retained<WgpuTriangleView>> {
+        ManuallyDrop::into_inner(self.0)
+    }
+}
+
 impl WgpuTriangleView {
-    pub fn new(mtm: MainThreadMarker, frame_rect: CGRect) -> Retained<Self> {
+    /// Create the view.
+    ///
+    /// The view does not draw anything until a renderer has been installed
+    /// with [`WgpuTriangleView::install_renderers_in_background`].
+    pub fn new(mtm: MainThreadMarker, frame_rect: CGRect, config: Config) -> Retained<Self> {
         // Create view
-        let view = mtm.alloc().set_ivars(OnceCell::new());
+        let view = mtm.alloc().set_ivars(Ivars {
+            config,
+            renderer: OnceCell::new(),
+        });
         let view: Retained<Self> = unsafe { msg_send![super(view), initWithFrame: frame_rect] };
 
-        // Set up wgpu state
-        let size = scaled_view_frame(&view);
-        let triangle = pollster::block_on(Triangle::new(
-            ViewWrapper(view.retain()),
-            size.width as u32,
-            size.height as u32,
-            1.0,
-        ));
-        if cfg!(feature = "immediate-redraw") {
-            triangle.redraw();
-        }
-        view.ivars().set(triangle).expect("only initialize once");
-
         // Listen for changes to the size of the view.
         //
         // This is done automatically on iOS with `layoutSubviews`.
@@ -238,17 +328,215 @@ impl WgpuTriangleView {
             }
         }
 
+        // Receive `mouseMoved:` and `mouseExited:` for the pointer position
+        // in the globals.
+        #[cfg(target_os = "macos")]
+        {
+            use objc2::AllocAnyThread;
+            use objc2_app_kit::{NSTrackingArea, NSTrackingAreaOptions};
+            let options = NSTrackingAreaOptions::MouseMoved
+                | NSTrackingAreaOptions::MouseEnteredAndExited
+                | NSTrackingAreaOptions::ActiveInKeyWindow
+                // Follows the size of the view.
+                | NSTrackingAreaOptions::InVisibleRect;
+            let tracking_area = unsafe {
+                NSTrackingArea::initWithRect_options_owner_userInfo(
+                    NSTrackingArea::alloc(),
+                    view.bounds(),
+                    options,
+                    Some(&view),
+                    None,
+                )
+            };
+            view.addTrackingArea(&tracking_area);
+        }
+
+        // Let the content behind the view show through its layer.
+        #[cfg(target_os = "macos")]
+        if config.transparent {
+            view.setWantsLayer(true);
+            if let Some(layer) = view.layer() {
+                layer.setOpaque(false);
+            }
+        }
+
         // Ensure that the view calls `drawRect:` after being resized
         #[cfg(not(target_os = "macos"))]
         view.setContentMode(objc2_ui_kit::UIViewContentMode::Redraw);
 
-        if cfg!(feature = "display-link") {
+        if config.display_link {
             view.redraw_with_displaylink();
         }
 
         view
     }
 
+    /// Create the shared GPU context on a background thread, so that the main
+    /// thread isn't blocked while requesting the adapter and device, and
+    /// install a renderer into each of the views once it's ready.
+    pub fn install_renderers_in_background(views: Vec<Retained<Self>>, config: Config) {
+        let views = ViewsForMainThread(ManuallyDrop::new(views));
+        thread::spawn(move || {
+            let start = Instant::now();
+            let mut gpu = match pollster::block_on(GpuContext::try_new()) {
+                Ok(gpu) => gpu,
+                Err(err) => {
+                    tracing::error!(%err, "failed creating GPU context");
+                    // Release the views on the main thread, where they belong.
+                    run_on_main(move |mtm| drop(views.into_inner(mtm)));
+                    return;
+                }
+            };
+            if let Some(path) = config.shader_path() {
+                gpu.enable_shader_hot_reload(path);
+            }
+            // The renderers switch to the new context on their next redraw.
+            gpu.set_recreated_callback(|| {
+                run_on_main(|_mtm| {
+                    VIEWS.with_borrow(|views| {
+                        for view in views.iter().filter_map(Weak::load) {
+                            view.request_redraw();
+                        }
+                    });
+                });
+            });
+            let gpu = Arc::new(gpu);
+            tracing::info!(elapsed = ?start.elapsed(), "created GPU context");
+
+            run_on_main(move |mtm| {
+                for view in views.into_inner(mtm) {
+                    view.install_renderer(&gpu);
+                }
+            });
+        });
+    }
+
+    fn install_renderer(&self, gpu: &Arc<GpuContext>) {
+        let config = self.ivars().config;
+        let size = scaled_view_frame(self);
+        let renderer = Renderer::new(
+            Arc::clone(gpu),
+            ViewWrapper(self.retain()),
+            size.width as u32,
+            size.height as u32,
+            self.scale_factor(),
+            config.present_modes(),
+            config.format_preference(),
+            Triangle::new(),
+        );
+        let renderer = match renderer {
+            Ok(renderer) => renderer,
+            Err(err) => {
+                tracing::error!(%err, "failed creating renderer, the view stays blank");
+                return;
+            }
+        };
+        renderer.set_sample_count(config.sample_count());
+        renderer.set_clear_color(config.clear_color());
+        if config.transparent {
+            renderer.set_transparent(true);
+        }
+        if let Some(latency) = config.frame_latency {
+            renderer.set_maximum_frame_latency(latency);
+        }
+        if let Some(refresh_rate) = self.refresh_rate() {
+            renderer.set_refresh_interval(Duration::from_secs_f64(1.0 / refresh_rate));
+        }
+        if config.immediate_redraw {
+            renderer.redraw();
+        }
+        self.ivars()
+            .renderer
+            .set(renderer)
+            .expect("only initialize once");
+        VIEWS.with_borrow_mut(|views| views.push(Weak::from(self)));
+
+        // Draw the first frame.
+        self.request_redraw();
+    }
+
+    /// Schedule `drawRect:` or `updateLayer` to be called.
+    fn request_redraw(&self) {
+        #[cfg(target_os = "macos")]
+        self.setNeedsDisplay(true);
+        #[cfg(not(target_os = "macos"))]
+        self.setNeedsDisplay();
+    }
+
+    /// The renderer, or `None` if it has not yet been installed.
+    fn renderer(&self) -> Option<&Renderer<WindowSurface<'static>>> {
+        let renderer = self.ivars().renderer.get();
+        if renderer.is_none() {
+            tracing::trace!("renderer not yet initialized, ignoring");
+        }
+        renderer
+    }
+
+    /// Update the pointer position in the globals from a mouse event, or
+    /// clear it with `None`.
+    #[cfg(target_os = "macos")]
+    fn set_pointer_position(&self, event: Option<&objc2_app_kit::NSEvent>) {
+        let Some(renderer) = self.renderer() else {
+            return;
+        };
+        let position = event.map(|event| {
+            let point = self.convertPoint_fromView(event.locationInWindow(), None);
+            // The globals are relative to the top left.
+            let y = if self.isFlipped() {
+                point.y
+            } else {
+                self.bounds().size.height - point.y
+            };
+            let scale_factor = self.scale_factor();
+            [point.x as f32 * scale_factor, y as f32 * scale_factor]
+        });
+        renderer.set_pointer_position(position);
+    }
+
+    /// Update the pointer position in the globals from a touch, or clear it
+    /// with `None`.
+    #[cfg(not(target_os = "macos"))]
+    fn set_pointer_position(&self, touch: Option<&objc2_ui_kit::UITouch>) {
+        let Some(renderer) = self.renderer() else {
+            return;
+        };
+        let position = touch.map(|touch| {
+            let point = touch.locationInView(Some(self));
+            let scale_factor = self.scale_factor();
+            [point.x as f32 * scale_factor, point.y as f32 * scale_factor]
+        });
+        renderer.set_pointer_position(position);
+    }
+
+    #[cfg(target_os = "macos")]
+    fn scale_factor(&self) -> f32 {
+        self.window()
+            .map(|window| window.backingScaleFactor() as f32)
+            .unwrap_or(1.0)
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn scale_factor(&self) -> f32 {
+        self.contentScaleFactor() as f32
+    }
+
+    /// The maximum refresh rate of the screen that the view is on, in Hz.
+    #[cfg(target_os = "macos")]
+    fn refresh_rate(&self) -> Option<f64> {
+        let screen = self.window()?.screen()?;
+        // Only available since macOS 12.
+        if !screen.respondsToSelector(sel!(maximumFramesPerSecond)) {
+            return None;
+        }
+        Some(screen.maximumFramesPerSecond() as f64).filter(|&rate| rate > 0.0)
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn refresh_rate(&self) -> Option<f64> {
+        let screen = self.window()?.screen();
+        Some(screen.maximumFramesPerSecond() as f64).filter(|&rate| rate > 0.0)
+    }
+
     fn redraw_with_displaylink(&self) {
         let display_link =
             unsafe { CADisplayLink::displayLinkWithTarget_selector(self, sel!(step:)) };
@@ -1,199 +1,190 @@
 //! Adapted from `wgpu/examples/src/hello_triangle`.
-use std::cell::RefCell;
-use wgpu::util::DeviceExt;
-
-use crate::frame_counter::FrameCounter;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::gpu_context::GpuContext;
+use crate::scene::{Scene, TargetFormat};
+
+/// The shader source embedded in the binary.
+const SHADER_SOURCE: &str = include_str!("shader.wgsl");
+
+/// A red triangle, whose top vertex is 200 points from the right edge.
+///
+/// The shader and pipelines are shared between all the triangles using the
+/// same [`GpuContext`]. The shader is reloaded when hot-reloading is enabled
+/// on the context, see [`GpuContext::enable_shader_hot_reload`].
+#[derive(Debug, Default)]
+pub struct Triangle {
+    // `None` until initialized.
+    resources: Option<Resources>,
+}
 
-#[allow(unused)] // Unsure which of these need to be kept around!
+/// The shader, and the pipelines created from it, which are shared through
+/// [`GpuContext::shared`].
 #[derive(Debug)]
-pub struct Triangle<'window> {
-    instance: wgpu::Instance,
-    surface: wgpu::Surface<'window>,
-    adapter: wgpu::Adapter,
-    device: wgpu::Device,
-    queue: wgpu::Queue,
-    shader: wgpu::ShaderModule,
+struct Pipelines {
     pipeline_layout: wgpu::PipelineLayout,
-    bind_group: wgpu::BindGroup,
-    uniform_buf: wgpu::Buffer,
+    shader: wgpu::ShaderModule,
+    // The generation of the reloaded shader that `shader` was compiled from,
+    // or that failed to compile, see `GpuContext::reloaded_shader`.
+    shader_generation: u64,
+    // Different targets may use different formats and sample counts, so we
+    // create a pipeline for each of them on demand.
+    render_pipelines: HashMap<TargetFormat, wgpu::RenderPipeline>,
+}
+
+/// The resources that must be recreated along with the device.
+#[derive(Debug)]
+struct Resources {
+    pipelines: Arc<Mutex<Pipelines>>,
+    format: TargetFormat,
+    // Taken from `pipelines`, so that rendering doesn't need to lock them.
     render_pipeline: wgpu::RenderPipeline,
-    config: RefCell<wgpu::SurfaceConfiguration>,
-    frame_counter: FrameCounter,
+    shader_generation: u64,
 }
 
-impl<'window> Triangle<'window> {
-    pub async fn new(
-        target: impl Into<wgpu::SurfaceTarget<'window>>,
-        width: u32,
-        height: u32,
-        scale_factor: f32,
-    ) -> Self {
-        let instance = wgpu::Instance::default();
+impl Triangle {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-        let surface = instance.create_surface(target).unwrap();
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                force_fallback_adapter: false,
-                // Request an adapter which can render to our surface
-                compatible_surface: Some(&surface),
-            })
-            .await
-            .expect("Failed to find an appropriate adapter");
+    /// The pipeline that the triangle is drawn with, `None` until
+    /// initialized.
+    pub fn render_pipeline(&self) -> Option<&wgpu::RenderPipeline> {
+        self.resources
+            .as_ref()
+            .map(|resources| &resources.render_pipeline)
+    }
+}
 
-        // Create the logical device and command queue
-        let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor {
-                label: None,
-                required_features: wgpu::Features::empty(),
-                // Make sure we use the texture resolution limits from the adapter, so we can support images the size of the swapchain.
-                required_limits: wgpu::Limits::downlevel_webgl2_defaults()
-                    .using_resolution(adapter.limits()),
-                experimental_features: wgpu::ExperimentalFeatures::disabled(),
-                memory_hints: wgpu::MemoryHints::Performance,
-                trace: wgpu::Trace::default(),
+impl Pipelines {
+    fn new(gpu: &GpuContext) -> Self {
+        let pipeline_layout =
+            gpu.device()
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: None,
+                    bind_group_layouts: &[gpu.globals_bind_group_layout()],
+                    immediate_size: 0,
+                });
+        Self {
+            pipeline_layout,
+            shader: gpu.create_shader_module("Triangle Shader", SHADER_SOURCE),
+            shader_generation: 0,
+            render_pipelines: HashMap::new(),
+        }
+    }
+
+    /// Get the pipeline for rendering into textures with the given formats
+    /// and number of samples per pixel.
+    fn render_pipeline(&mut self, gpu: &GpuContext, format: TargetFormat) -> wgpu::RenderPipeline {
+        self.render_pipelines
+            .entry(format)
+            .or_insert_with(|| {
+                tracing::debug!(?format, "creating render pipeline");
+                create_render_pipeline(gpu, &self.pipeline_layout, &self.shader, format)
             })
-            .await
-            .expect("Failed to create device");
+            .clone()
+    }
 
-        // Load the shaders from disk
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: None,
-            source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
+    /// Recreate the pipelines from the given generation of the reloaded
+    /// shader, unless another triangle already did.
+    ///
+    /// If the pipelines cannot be created from the new shader, the old ones
+    /// are kept.
+    fn reload_shader(&mut self, gpu: &GpuContext, shader: wgpu::ShaderModule, generation: u64) {
+        if generation <= self.shader_generation {
+            return;
+        }
+        self.shader_generation = generation;
+        let render_pipelines = gpu.catch_validation_error(|| {
+            self.render_pipelines
+                .keys()
+                .map(|&format| {
+                    let render_pipeline =
+                        create_render_pipeline(gpu, &self.pipeline_layout, &shader, format);
+                    (format, render_pipeline)
+                })
+                .collect()
         });
+        match render_pipelines {
+            Ok(render_pipelines) => {
+                self.shader = shader;
+                self.render_pipelines = render_pipelines;
+            }
+            Err(err) => {
+                tracing::error!("failed recreating the pipelines, keeping the old ones:\n{err}");
+            }
+        }
+    }
+}
 
-        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: None,
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: wgpu::BufferSize::new(4),
-                },
-                count: None,
-            }],
-        });
-        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: None,
-            bind_group_layouts: &[&bind_group_layout],
-            immediate_size: 0,
+impl Scene for Triangle {
+    fn init(&mut self, gpu: &GpuContext, format: TargetFormat) {
+        let pipelines = gpu.shared(|| Mutex::new(Pipelines::new(gpu)));
+        let mut locked = pipelines.lock().unwrap();
+        let render_pipeline = locked.render_pipeline(gpu, format);
+        let shader_generation = locked.shader_generation;
+        drop(locked);
+        self.resources = Some(Resources {
+            pipelines,
+            format,
+            render_pipeline,
+            shader_generation,
         });
+    }
 
-        let uniform_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Uniform Buffer"),
-            contents: bytemuck::cast_slice(&[width as f32 / scale_factor]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
+    // The width is read from the globals.
+    fn resize(&mut self, _gpu: &GpuContext, _width: u32, _height: u32, _scale_factor: f32) {}
 
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: uniform_buf.as_entire_binding(),
-            }],
-            label: None,
-        });
+    fn update(&mut self, gpu: &GpuContext, _dt: Duration) {
+        let Some(resources) = &mut self.resources else {
+            return;
+        };
+        if let Some(shader) = gpu.reloaded_shader(&mut resources.shader_generation) {
+            let mut pipelines = resources.pipelines.lock().unwrap();
+            pipelines.reload_shader(gpu, shader, resources.shader_generation);
+            resources.render_pipeline = pipelines.render_pipeline(gpu, resources.format);
+        }
+    }
 
-        let swapchain_capabilities = surface.get_capabilities(&adapter);
-        let swapchain_format = swapchain_capabilities.formats[0];
+    fn render(&mut self, rpass: &mut wgpu::RenderPass<'_>) {
+        let Some(resources) = &self.resources else {
+            return;
+        };
+        rpass.set_pipeline(&resources.render_pipeline);
+        rpass.draw(0..3, 0..1);
+    }
+}
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+fn create_render_pipeline(
+    gpu: &GpuContext,
+    pipeline_layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    format: TargetFormat,
+) -> wgpu::RenderPipeline {
+    gpu.create_render_pipeline(
+        &format!("Triangle {format:?}"),
+        &wgpu::RenderPipelineDescriptor {
             label: None,
-            layout: Some(&pipeline_layout),
+            layout: Some(pipeline_layout),
             vertex: wgpu::VertexState {
-                module: &shader,
+                module: shader,
                 entry_point: Some("vs_main"),
                 buffers: &[],
                 compilation_options: Default::default(),
             },
             fragment: Some(wgpu::FragmentState {
-                module: &shader,
+                module: shader,
                 entry_point: Some("fs_main"),
                 compilation_options: Default::default(),
-                targets: &[Some(swapchain_format.into())],
+                targets: &[Some(format.format.into())],
             }),
             primitive: wgpu::PrimitiveState::default(),
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
+            depth_stencil: format.depth_stencil(),
+            multisample: format.multisample(),
             multiview_mask: None,
             cache: None,
-        });
-
-        let mut config = surface.get_default_config(&adapter, width, height).unwrap();
-        config.present_mode = if cfg!(feature = "no-vsync") {
-            wgpu::PresentMode::Immediate
-        } else {
-            wgpu::PresentMode::default()
-        };
-        surface.configure(&device, &config);
-
-        Self {
-            instance,
-            surface,
-            adapter,
-            device,
-            queue,
-            shader,
-            pipeline_layout,
-            uniform_buf,
-            bind_group,
-            render_pipeline,
-            config: RefCell::new(config),
-            frame_counter: FrameCounter::new(),
-        }
-    }
-
-    pub fn resize(&self, width: u32, height: u32, scale_factor: f32) {
-        self.queue.write_buffer(
-            &self.uniform_buf,
-            0,
-            bytemuck::cast_slice(&[width as f32 / scale_factor]),
-        );
-
-        let mut config = self.config.borrow_mut();
-        config.width = width;
-        config.height = height;
-        self.surface.configure(&self.device, &config);
-    }
-
-    pub fn redraw(&self) {
-        let frame = self
-            .surface
-            .get_current_texture()
-            .expect("Failed to acquire next swap chain texture");
-        let view = frame
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
-        {
-            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    depth_slice: None,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::GREEN),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                timestamp_writes: None,
-                occlusion_query_set: None,
-                multiview_mask: None,
-            });
-            rpass.set_pipeline(&self.render_pipeline);
-            rpass.set_bind_group(0, &self.bind_group, &[]);
-            rpass.draw(0..3, 0..1);
-        }
-
-        self.queue.submit(Some(encoder.finish()));
-        frame.present();
-
-        self.frame_counter.update();
-    }
+        },
+    )
 }
@@ -0,0 +1,21 @@
+use wgpu::CompositeAlphaMode::*;
+use wgpu_objc2_example::alpha_mode::negotiate;
+
+#[test]
+fn opaque() {
+    assert_eq!(negotiate(false, &[PostMultiplied, Opaque]), Opaque);
+    assert_eq!(negotiate(false, &[PostMultiplied, Inherit]), Inherit);
+    assert_eq!(negotiate(false, &[PostMultiplied]), PostMultiplied);
+}
+
+#[test]
+fn transparent() {
+    assert_eq!(negotiate(true, &[Opaque, PostMultiplied]), PostMultiplied);
+    assert_eq!(
+        negotiate(true, &[Opaque, PostMultiplied, PreMultiplied]),
+        PreMultiplied
+    );
+    // Not supported by the surface, so it can't be transparent.
+    assert_eq!(negotiate(true, &[Opaque]), Opaque);
+    assert_eq!(negotiate(true, &[]), Auto);
+}
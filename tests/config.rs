@@ -0,0 +1,99 @@
+use wgpu_objc2_example::config::{Config, ConfigError};
+use wgpu_objc2_example::present_mode;
+
+fn parse(args: &[&str], env: &[(&str, &str)]) -> Result<Config, ConfigError> {
+    Config::parse(args.iter().map(|arg| arg.to_string()), |name| {
+        env.iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.to_string())
+    })
+}
+
+#[test]
+fn defaults() {
+    assert_eq!(parse(&[], &[]), Ok(Config::default()));
+    assert_eq!(Config::default().present_modes(), present_mode::VSYNC);
+    assert_eq!(Config::default().sample_count(), 1);
+}
+
+#[test]
+fn flags() {
+    let config = parse(&["--immediate-redraw", "--no-vsync", "--msaa=4"], &[]).unwrap();
+    assert!(config.immediate_redraw);
+    assert!(config.no_vsync);
+    assert_eq!(config.sample_count(), 4);
+    assert!(!config.display_link);
+    assert_eq!(config.present_modes(), present_mode::NO_VSYNC);
+    assert!(config.redraw_on_resize());
+}
+
+#[test]
+fn env_and_flags_combine() {
+    let env = [
+        ("WGPU_OBJC2_EXAMPLE_DISPLAY_LINK", "1"),
+        ("WGPU_OBJC2_EXAMPLE_TWO_TRIANGLES", "0"),
+    ];
+    let config = parse(&["--immediate-redraw"], &env).unwrap();
+    assert!(config.display_link);
+    assert!(!config.two_triangles);
+    assert!(config.immediate_redraw);
+    assert!(!config.redraw_on_resize());
+}
+
+#[test]
+fn frame_latency() {
+    assert_eq!(parse(&[], &[]).unwrap().frame_latency, None);
+    let env = [("WGPU_OBJC2_EXAMPLE_FRAME_LATENCY", "3")];
+    assert_eq!(parse(&[], &env).unwrap().frame_latency, Some(3));
+    let config = parse(&["--frame-latency=1", "--queue-display"], &env).unwrap();
+    assert_eq!(config.frame_latency, Some(1));
+    assert!(config.queue_display);
+}
+
+#[test]
+fn msaa() {
+    let env = [("WGPU_OBJC2_EXAMPLE_MSAA", "8")];
+    assert_eq!(parse(&[], &env).unwrap().sample_count(), 8);
+    assert_eq!(parse(&["--msaa=2"], &env).unwrap().sample_count(), 2);
+    assert_eq!(parse(&["--msaa=1"], &env).unwrap().msaa, None);
+}
+
+#[test]
+fn errors() {
+    assert_eq!(parse(&["--help"], &[]), Err(ConfigError::Help));
+    assert_eq!(
+        parse(&["--vsync"], &[]),
+        Err(ConfigError::UnknownFlag("--vsync".into()))
+    );
+    assert_eq!(
+        parse(&[], &[("WGPU_OBJC2_EXAMPLE_DRAW_RECT", "maybe")]),
+        Err(ConfigError::InvalidEnvValue {
+            name: "WGPU_OBJC2_EXAMPLE_DRAW_RECT".into(),
+            value: "maybe".into(),
+        })
+    );
+    assert_eq!(
+        parse(&["--frame-latency=0"], &[]),
+        Err(ConfigError::InvalidFrameLatency("0".into()))
+    );
+    assert_eq!(
+        parse(&["--msaa=3"], &[]),
+        Err(ConfigError::InvalidSampleCount("3".into()))
+    );
+    assert_eq!(
+        parse(&[], &[("WGPU_OBJC2_EXAMPLE_MSAA", "true")]),
+        Err(ConfigError::InvalidSampleCount("true".into()))
+    );
+    assert_eq!(
+        parse(&["--frame-latency"], &[]),
+        Err(ConfigError::MissingValue("frame-latency".into()))
+    );
+    assert_eq!(
+        ConfigError::MissingValue("frame-latency".into()).to_string(),
+        "missing value for --frame-latency, expected --frame-latency=N"
+    );
+    assert_eq!(
+        parse(&["--msaa"], &[]),
+        Err(ConfigError::MissingValue("msaa".into()))
+    );
+}
@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use wgpu_objc2_example::frame_counter::FrameTimes;
+
+const REFRESH_INTERVAL: Duration = Duration::from_millis(10);
+
+fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+#[test]
+fn empty() {
+    assert_eq!(FrameTimes::new(10).stats(10, REFRESH_INTERVAL), None);
+}
+
+#[test]
+fn percentiles() {
+    let mut frame_times = FrameTimes::new(100);
+    for i in 1..=100 {
+        frame_times.push(ms(i));
+    }
+    let stats = frame_times.stats(100, REFRESH_INTERVAL).unwrap();
+    assert_eq!(stats.min, ms(1));
+    assert_eq!(stats.avg, Duration::from_micros(50_500));
+    assert_eq!(stats.p50, ms(50));
+    assert_eq!(stats.p95, ms(95));
+    assert_eq!(stats.p99, ms(99));
+    assert_eq!(stats.max, ms(100));
+    // Everything above 15ms.
+    assert_eq!(stats.hitches, 85);
+}
+
+#[test]
+fn only_last() {
+    let mut frame_times = FrameTimes::new(100);
+    frame_times.push(ms(100));
+    for _ in 0..3 {
+        frame_times.push(ms(10));
+    }
+    let stats = frame_times.stats(3, REFRESH_INTERVAL).unwrap();
+    assert_eq!(stats.max, ms(10));
+    assert_eq!(stats.hitches, 0);
+    assert_eq!(frame_times.stats(4, REFRESH_INTERVAL).unwrap().hitches, 1);
+}
+
+#[test]
+fn ring_buffer_forgets_oldest() {
+    let mut frame_times = FrameTimes::new(3);
+    for i in 1..=5 {
+        frame_times.push(ms(i));
+    }
+    let stats = frame_times.stats(100, REFRESH_INTERVAL).unwrap();
+    assert_eq!(stats.min, ms(3));
+    assert_eq!(stats.max, ms(5));
+}
+
+#[test]
+#[should_panic = "non-zero capacity"]
+fn zero_capacity() {
+    FrameTimes::new(0);
+}
@@ -0,0 +1,135 @@
+use std::time::Duration;
+
+use wgpu_objc2_example::globals::Globals;
+use wgpu_objc2_example::gpu_context::{shader_prelude_lines, GpuContext};
+use wgpu_objc2_example::renderer::Renderer;
+use wgpu_objc2_example::scene::{Scene, TargetFormat};
+use wgpu_objc2_example::wgpu_triangle::Triangle;
+
+/// A scene that fills the target with a color computed from the globals.
+#[derive(Debug, Default)]
+struct GlobalsScene {
+    render_pipeline: Option<wgpu::RenderPipeline>,
+}
+
+impl Scene for GlobalsScene {
+    fn init(&mut self, gpu: &GpuContext, format: TargetFormat) {
+        let shader = gpu.create_shader_module(
+            "Globals Shader",
+            "
+            @vertex
+            fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+                let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+                return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+            }
+
+            @fragment
+            fn fs_main() -> @location(0) vec4<f32> {
+                return vec4<f32>(
+                    select(0.0, 1.0, globals.pointer.x >= 0.0),
+                    select(0.0, 1.0, globals.frame == 1u),
+                    select(0.0, 1.0, globals.logical_resolution.x * globals.scale_factor == globals.resolution.x),
+                    1.0,
+                );
+            }
+            ",
+        );
+        let device = gpu.device();
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[gpu.globals_bind_group_layout()],
+            immediate_size: 0,
+        });
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(format.format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: format.depth_stencil(),
+            multisample: format.multisample(),
+            multiview_mask: None,
+            cache: None,
+        });
+        self.render_pipeline = Some(render_pipeline);
+    }
+
+    fn resize(&mut self, _gpu: &GpuContext, _width: u32, _height: u32, _scale_factor: f32) {}
+
+    fn update(&mut self, _gpu: &GpuContext, _dt: Duration) {}
+
+    fn render(&mut self, rpass: &mut wgpu::RenderPass<'_>) {
+        rpass.set_pipeline(self.render_pipeline.as_ref().unwrap());
+        rpass.draw(0..3, 0..1);
+    }
+}
+
+#[test]
+fn values() {
+    let renderer = pollster::block_on(Renderer::new_headless(64, 48, 2.0, Triangle::new()));
+    assert_eq!(renderer.globals(), Globals::default());
+
+    renderer.redraw();
+    let globals = renderer.globals();
+    assert_eq!(globals.delta_time, 0.0);
+    assert_eq!(globals.frame, 0);
+    assert_eq!(globals.scale_factor, 2.0);
+    assert_eq!(globals.resolution, [64.0, 48.0]);
+    assert_eq!(globals.logical_resolution, [32.0, 24.0]);
+    assert_eq!(globals.pointer, Globals::NO_POINTER);
+
+    std::thread::sleep(Duration::from_millis(10));
+    renderer.set_pointer_position(Some([10.0, 20.0]));
+    renderer.resize(32, 24, 1.0);
+    renderer.redraw();
+    let next = renderer.globals();
+    assert!(next.delta_time >= 0.01, "{next:?}");
+    assert!(next.time > globals.time, "{next:?}");
+    assert_eq!(next.frame, 1);
+    assert_eq!(next.scale_factor, 1.0);
+    assert_eq!(next.resolution, [32.0, 24.0]);
+    assert_eq!(next.logical_resolution, [32.0, 24.0]);
+    assert_eq!(next.pointer, [10.0, 20.0]);
+
+    renderer.set_pointer_position(None);
+    renderer.redraw();
+    assert_eq!(renderer.globals().pointer, Globals::NO_POINTER);
+}
+
+#[test]
+fn bound_for_scenes() {
+    let renderer = pollster::block_on(Renderer::new_headless(8, 8, 2.0, GlobalsScene::default()));
+    renderer.redraw();
+    assert_eq!(renderer.read_frame()[..4], [0, 0, 255, 255]);
+
+    renderer.set_pointer_position(Some([1.0, 1.0]));
+    renderer.redraw();
+    assert_eq!(renderer.read_frame()[..4], [255, 255, 255, 255]);
+}
+
+#[test]
+fn shader_errors_are_offset_by_the_prelude() {
+    let gpu = pollster::block_on(GpuContext::new_headless());
+    let err = gpu
+        .catch_validation_error(|| {
+            gpu.create_shader_module(
+                "Broken Shader",
+                "\n\nfn broken() -> f32 { return missing; }\n",
+            )
+        })
+        .unwrap_err()
+        .to_string();
+    let line = 3 + shader_prelude_lines();
+    assert!(err.contains(&format!(":{line}:")), "{err}");
+    assert!(err.contains("Broken Shader"), "{err}");
+}
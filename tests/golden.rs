@@ -0,0 +1,320 @@
+//! Golden-image tests for the triangle output.
+//!
+//! Each test renders the triangle headlessly and compares the result against
+//! a reference PNG in `tests/golden/`. On a mismatch, the rendered image and a
+//! diff image are written next to the test binaries, see the panic message.
+//!
+//! Run with `UPDATE_GOLDEN=1` to (re-)generate the reference images.
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use wgpu_objc2_example::gpu_context::GpuContext;
+use wgpu_objc2_example::model::Model;
+use wgpu_objc2_example::model_scene::ModelScene;
+use wgpu_objc2_example::render_target::{ExternalView, OffscreenTexture, RenderTarget};
+use wgpu_objc2_example::renderer::{DepthFormatError, Renderer};
+use wgpu_objc2_example::wgpu_triangle::Triangle;
+
+/// Maximum allowed difference per color channel, to allow for small
+/// rasterization differences between (software) adapters.
+const CHANNEL_TOLERANCE: u8 = 2;
+
+/// Maximum number of pixels that may exceed the channel tolerance, to allow
+/// for edge pixels being rasterized slightly differently.
+const MAX_MISMATCHED_PIXELS: usize = 8;
+
+#[test]
+fn small() {
+    check_golden("small", 64, 48, 1.0);
+}
+
+#[test]
+fn small_scaled() {
+    check_golden("small_scaled", 128, 96, 2.0);
+}
+
+#[test]
+fn wide() {
+    check_golden("wide", 320, 120, 1.0);
+}
+
+#[test]
+fn wide_scaled() {
+    check_golden("wide_scaled", 320, 120, 2.0);
+}
+
+#[test]
+fn tall_scaled() {
+    check_golden("tall_scaled", 150, 300, 3.0);
+}
+
+#[test]
+fn resized() {
+    let triangle = pollster::block_on(Renderer::new_headless(64, 48, 1.0, Triangle::new()));
+    triangle.resize(320, 120, 2.0);
+    triangle.redraw();
+    compare("resized", 320, 120, &triangle.read_frame());
+}
+
+#[test]
+fn msaa() {
+    let triangle = pollster::block_on(Renderer::new_headless(64, 48, 1.0, Triangle::new()));
+    triangle.set_sample_count(4);
+    assert_eq!(triangle.sample_count(), 4);
+    triangle.redraw();
+    compare("msaa", 64, 48, &triangle.read_frame());
+
+    // Switching back at runtime uses the non-multisampled pipeline again.
+    triangle.set_sample_count(1);
+    triangle.redraw();
+    compare("small", 64, 48, &triangle.read_frame());
+}
+
+#[test]
+fn depth_formats() {
+    let triangle = pollster::block_on(Renderer::new_headless(64, 48, 1.0, Triangle::new()));
+    for depth_format in [
+        None,
+        Some(wgpu::TextureFormat::Depth16Unorm),
+        Some(wgpu::TextureFormat::Depth24PlusStencil8),
+        Some(wgpu::TextureFormat::Stencil8),
+    ] {
+        triangle.set_depth_format(depth_format).unwrap();
+        triangle.redraw();
+        compare("small", 64, 48, &triangle.read_frame());
+    }
+
+    // Left unchanged if the format is not usable.
+    assert_eq!(
+        triangle.set_depth_format(Some(wgpu::TextureFormat::Rgba8Unorm)),
+        Err(DepthFormatError::NotDepthStencil(
+            wgpu::TextureFormat::Rgba8Unorm
+        ))
+    );
+    let format = wgpu::TextureFormat::Depth32FloatStencil8;
+    if !triangle
+        .gpu()
+        .device()
+        .features()
+        .contains(format.required_features())
+    {
+        assert_eq!(
+            triangle.set_depth_format(Some(format)),
+            Err(DepthFormatError::MissingFeatures {
+                format,
+                features: wgpu::Features::DEPTH32FLOAT_STENCIL8,
+            })
+        );
+    }
+    assert_eq!(triangle.depth_format(), Some(wgpu::TextureFormat::Stencil8));
+
+    // The depth texture is recreated along with the multisampled texture.
+    triangle.set_sample_count(4);
+    triangle.resize(64, 48, 1.0);
+    triangle.redraw();
+    compare("msaa", 64, 48, &triangle.read_frame());
+}
+
+#[test]
+fn clear_color() {
+    let triangle = pollster::block_on(Renderer::new_headless(64, 48, 1.0, Triangle::new()));
+    // Offscreen textures store the alpha as-is, without premultiplying.
+    triangle.set_clear_color(wgpu::Color {
+        r: 0.0,
+        g: 0.0,
+        b: 1.0,
+        a: 0.5,
+    });
+    triangle.redraw();
+    compare("clear_color", 64, 48, &triangle.read_frame());
+}
+
+#[test]
+fn external_view() {
+    let instance = wgpu::Instance::default();
+    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        force_fallback_adapter: true,
+        ..Default::default()
+    }))
+    .unwrap();
+    let (device, queue) =
+        pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default())).unwrap();
+
+    // Stand-in for a texture owned by some other part of an engine.
+    let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+    let texture = OffscreenTexture::new(&device, format, 64, 48);
+    let view = texture.next_frame(&device).unwrap().view().clone();
+
+    let gpu = GpuContext::from_device(instance, adapter, device.clone(), queue.clone());
+    let triangle = Renderer::from_target(
+        Arc::new(gpu),
+        ExternalView::new(view, format),
+        1.0,
+        Triangle::new(),
+    );
+    triangle.redraw();
+    compare("small", 64, 48, &texture.read_back(&device, &queue));
+}
+
+#[test]
+fn shared_context() {
+    let gpu = Arc::new(pollster::block_on(GpuContext::new_headless()));
+    let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+    let small = OffscreenTexture::new(gpu.device(), format, 64, 48);
+    let wide = OffscreenTexture::new(gpu.device(), format, 320, 120);
+    let small = Renderer::from_target(Arc::clone(&gpu), small, 1.0, Triangle::new());
+    let wide = Renderer::from_target(Arc::clone(&gpu), wide, 2.0, Triangle::new());
+
+    small.redraw();
+    wide.redraw();
+    let read_back = |triangle: &Renderer<OffscreenTexture>| {
+        triangle.target().read_back(gpu.device(), gpu.queue())
+    };
+    compare("small", 64, 48, &read_back(&small));
+    compare("wide_scaled", 320, 120, &read_back(&wide));
+}
+
+#[test]
+fn device_lost() {
+    let triangle = pollster::block_on(Renderer::new_headless(64, 48, 1.0, Triangle::new()));
+    let lost_gpu = triangle.gpu();
+    lost_gpu.device().destroy();
+    // The device lost callback is run when polling.
+    let _ = lost_gpu.device().poll(wgpu::PollType::Poll);
+    assert!(lost_gpu.is_lost());
+
+    // The replacement is requested in the background, and picked up by a
+    // later redraw.
+    let start = Instant::now();
+    while Arc::ptr_eq(&lost_gpu, &triangle.gpu()) {
+        assert!(start.elapsed() < Duration::from_secs(30), "not recreated");
+        triangle.redraw();
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    compare("small", 64, 48, &triangle.read_frame());
+}
+
+#[test]
+fn model_gltf_embedded() {
+    check_model_golden("model_gltf_embedded", "cube.gltf");
+}
+
+#[test]
+fn model_gltf_separate_files() {
+    check_model_golden("model_gltf_separate_files", "quad.gltf");
+}
+
+#[test]
+fn model_obj() {
+    check_model_golden("model_obj", "cube.obj");
+}
+
+#[test]
+fn model_obj_without_normals() {
+    check_model_golden("model_obj_without_normals", "tetrahedron.obj");
+}
+
+/// Render a model from `tests/models/`.
+fn check_model_golden(name: &str, file: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/models")
+        .join(file);
+    let model = Model::load(path).unwrap();
+    let renderer = pollster::block_on(Renderer::new_headless(96, 96, 1.0, ModelScene::new(model)));
+    renderer.redraw();
+    compare(name, 96, 96, &renderer.read_frame());
+}
+
+fn check_golden(name: &str, width: u32, height: u32, scale_factor: f32) {
+    let triangle = pollster::block_on(Renderer::new_headless(
+        width,
+        height,
+        scale_factor,
+        Triangle::new(),
+    ));
+    triangle.redraw();
+    compare(name, width, height, &triangle.read_frame());
+}
+
+fn compare(name: &str, width: u32, height: u32, actual: &[u8]) {
+    let reference_path = golden_dir().join(format!("{name}.png"));
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        write_png(&reference_path, width, height, actual);
+        return;
+    }
+
+    let (reference_width, reference_height, expected) = read_png(&reference_path);
+    assert_eq!(
+        (width, height),
+        (reference_width, reference_height),
+        "size of {name} does not match the reference image",
+    );
+
+    let mut mismatched = 0;
+    let mut diff = Vec::with_capacity(actual.len());
+    for (actual, expected) in actual.chunks_exact(4).zip(expected.chunks_exact(4)) {
+        let max_difference = actual
+            .iter()
+            .zip(expected)
+            .map(|(a, e)| a.abs_diff(*e))
+            .max()
+            .unwrap();
+        if max_difference > CHANNEL_TOLERANCE {
+            mismatched += 1;
+            diff.extend_from_slice(&[255, 0, 255, 255]);
+        } else {
+            // Dim the matching pixels, so that the mismatches stand out.
+            diff.extend(actual[..3].iter().map(|c| c / 4));
+            diff.push(255);
+        }
+    }
+
+    if mismatched > MAX_MISMATCHED_PIXELS {
+        let output_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden");
+        std::fs::create_dir_all(&output_dir).unwrap();
+        let actual_path = output_dir.join(format!("{name}.actual.png"));
+        let diff_path = output_dir.join(format!("{name}.diff.png"));
+        write_png(&actual_path, width, height, actual);
+        write_png(&diff_path, width, height, &diff);
+        panic!(
+            "{name}: {mismatched} pixels differ from {}, see {} and {}",
+            reference_path.display(),
+            actual_path.display(),
+            diff_path.display(),
+        );
+    }
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+fn read_png(path: &Path) -> (u32, u32, Vec<u8>) {
+    let file = File::open(path).unwrap_or_else(|err| {
+        panic!(
+            "failed opening {}: {err}, run with UPDATE_GOLDEN=1 to create it",
+            path.display()
+        )
+    });
+    let mut reader = png::Decoder::new(BufReader::new(file)).read_info().unwrap();
+    let mut buf = vec![0; reader.output_buffer_size().unwrap()];
+    let info = reader.next_frame(&mut buf).unwrap();
+    assert_eq!(info.color_type, png::ColorType::Rgba);
+    assert_eq!(info.bit_depth, png::BitDepth::Eight);
+    buf.truncate(info.buffer_size());
+    (info.width, info.height, buf)
+}
+
+fn write_png(path: &Path, width: u32, height: u32, pixels: &[u8]) {
+    let file = File::create(path).unwrap();
+    let mut encoder = png::Encoder::new(file, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(pixels).unwrap();
+}
@@ -0,0 +1,81 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use wgpu_objc2_example::frame_counter::FrameCounter;
+use wgpu_objc2_example::gpu_context::GpuContext;
+use wgpu_objc2_example::gpu_timer::GpuTimer;
+use wgpu_objc2_example::stats_sink::MemorySink;
+
+/// Clear a small texture in a render pass that is measured by `timer`.
+fn render_measured_frame(gpu: &GpuContext, timer: &GpuTimer) {
+    let texture = gpu.device().create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width: 4,
+            height: 4,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let mut encoder = gpu
+        .device()
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: None,
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: &view,
+            depth_slice: None,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::GREEN),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: Some(timer.timestamp_writes()),
+        occlusion_query_set: None,
+        multiview_mask: None,
+    });
+    timer.resolve(&mut encoder);
+    gpu.queue().submit(Some(encoder.finish()));
+}
+
+#[test]
+fn reports_gpu_time() {
+    let gpu = pollster::block_on(GpuContext::new_headless());
+    let Some(timer) = GpuTimer::new(gpu.device(), gpu.queue()) else {
+        eprintln!("timestamp queries are not supported, skipping");
+        return;
+    };
+    let sink = MemorySink::new();
+    let frame_counter = FrameCounter::with_sink(Box::new(sink.clone()));
+
+    assert!(timer.is_ready());
+    render_measured_frame(&gpu, &timer);
+    timer.read_back(frame_counter.gpu_time_recorder());
+    // Frames are not measured until the timestamps have been read back.
+    assert!(!timer.is_ready());
+
+    gpu.device()
+        .poll(wgpu::PollType::wait_indefinitely())
+        .unwrap();
+    assert!(timer.is_ready());
+
+    // The GPU time is reported along with the next statistics.
+    let start = Instant::now();
+    let gpu_ms = loop {
+        if let Some(report) = sink.reports().first() {
+            break report.gpu_ms;
+        }
+        assert!(start.elapsed() < Duration::from_secs(30), "no report");
+        thread::sleep(Duration::from_millis(50));
+    };
+    let gpu_ms = gpu_ms.expect("GPU time was recorded");
+    assert!(gpu_ms >= 0.0, "{gpu_ms}");
+}
@@ -0,0 +1,78 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use wgpu_objc2_example::gpu_context::GpuContext;
+use wgpu_objc2_example::render_target::OffscreenTexture;
+use wgpu_objc2_example::renderer::Renderer;
+use wgpu_objc2_example::wgpu_triangle::Triangle;
+
+const SHADER: &str = include_str!("../src/shader.wgsl");
+const RED: &str = "vec4<f32>(1.0, 0.0, 0.0, 1.0)";
+const BLUE: &str = "vec4<f32>(0.0, 0.0, 1.0, 1.0)";
+
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 48;
+
+/// A pixel near the bottom center, which is covered by the triangle.
+fn triangle_pixel(triangle: &Renderer<OffscreenTexture>) -> [u8; 4] {
+    let gpu = triangle.gpu();
+    let pixels = triangle.target().read_back(gpu.device(), gpu.queue());
+    let offset = (((HEIGHT - 2) * WIDTH + WIDTH / 2) * 4) as usize;
+    pixels[offset..offset + 4].try_into().unwrap()
+}
+
+/// Redraw until the triangle has the given color, or panic after a timeout.
+fn wait_for_color(triangle: &Renderer<OffscreenTexture>, color: [u8; 4]) {
+    let start = Instant::now();
+    loop {
+        triangle.redraw();
+        if triangle_pixel(triangle) == color {
+            return;
+        }
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "shader was not reloaded"
+        );
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn reloads_changed_shader() {
+    assert!(SHADER.contains(RED));
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("hot_reload");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("shader.wgsl");
+    std::fs::write(&path, SHADER).unwrap();
+
+    let mut gpu = pollster::block_on(GpuContext::new_headless());
+    gpu.enable_shader_hot_reload(&path);
+    let gpu = Arc::new(gpu);
+    let target = OffscreenTexture::new(
+        gpu.device(),
+        wgpu::TextureFormat::Rgba8UnormSrgb,
+        WIDTH,
+        HEIGHT,
+    );
+    let triangle = Renderer::from_target(gpu, target, 1.0, Triangle::new());
+
+    triangle.redraw();
+    assert_eq!(triangle_pixel(&triangle), [255, 0, 0, 255]);
+
+    std::fs::write(&path, SHADER.replace(RED, BLUE)).unwrap();
+    wait_for_color(&triangle, [0, 0, 255, 255]);
+
+    // An invalid shader is rejected, and the previous one is kept.
+    std::fs::write(&path, "this is not WGSL").unwrap();
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(1) {
+        triangle.redraw();
+        assert_eq!(triangle_pixel(&triangle), [0, 0, 255, 255]);
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    // And fixing the shader again makes it reload.
+    std::fs::write(&path, SHADER).unwrap();
+    wait_for_color(&triangle, [255, 0, 0, 255]);
+}
@@ -0,0 +1,125 @@
+use std::mem::offset_of;
+
+use wgpu_objc2_example::mesh::{Mesh, MeshScene, Vertex};
+use wgpu_objc2_example::renderer::Renderer;
+
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 48;
+
+const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+const BLUE: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+
+/// An axis-aligned rectangle in normalized device coordinates.
+fn rect(x0: f32, y0: f32, x1: f32, y1: f32, color: [f32; 4]) -> ([Vertex; 4], [u32; 6]) {
+    let vertices = [
+        Vertex::new([x0, y0, 0.0], color, [0.0, 1.0]),
+        Vertex::new([x1, y0, 0.0], color, [1.0, 1.0]),
+        Vertex::new([x1, y1, 0.0], color, [1.0, 0.0]),
+        Vertex::new([x0, y1, 0.0], color, [0.0, 0.0]),
+    ];
+    (vertices, [0, 1, 2, 0, 2, 3])
+}
+
+fn pixel(frame: &[u8], x: u32, y: u32) -> [u8; 4] {
+    let offset = ((y * WIDTH + x) * 4) as usize;
+    frame[offset..offset + 4].try_into().unwrap()
+}
+
+#[test]
+fn layout() {
+    assert_eq!(Vertex::LAYOUT.array_stride, size_of::<Vertex>() as u64);
+    // Each attribute must start at its field, with the field's size.
+    let fields = [
+        (offset_of!(Vertex, position), size_of::<[f32; 3]>()),
+        (offset_of!(Vertex, color), size_of::<[f32; 4]>()),
+        (offset_of!(Vertex, uv), size_of::<[f32; 2]>()),
+    ];
+    assert_eq!(Vertex::LAYOUT.attributes.len(), fields.len());
+    for (location, (attribute, (offset, size))) in
+        Vertex::LAYOUT.attributes.iter().zip(fields).enumerate()
+    {
+        assert_eq!(attribute.shader_location, location as u32);
+        assert_eq!(attribute.offset, offset as u64, "location {location}");
+        assert_eq!(attribute.format.size(), size as u64, "location {location}");
+    }
+}
+
+#[test]
+fn replace_and_append() {
+    let mesh = Mesh::new();
+    let renderer = pollster::block_on(Renderer::new_headless(
+        WIDTH,
+        HEIGHT,
+        1.0,
+        MeshScene::new(mesh.clone()),
+    ));
+    let (left, right) = (WIDTH / 4, WIDTH * 3 / 4);
+
+    renderer.redraw();
+    let frame = renderer.read_frame();
+    assert_eq!(pixel(&frame, left, HEIGHT / 2), [0, 255, 0, 255]);
+
+    let (vertices, indices) = rect(-1.0, -1.0, 0.0, 1.0, RED);
+    mesh.replace(&vertices, &indices);
+    renderer.redraw();
+    let frame = renderer.read_frame();
+    assert_eq!(pixel(&frame, left, HEIGHT / 2), [255, 0, 0, 255]);
+    assert_eq!(pixel(&frame, right, HEIGHT / 2), [0, 255, 0, 255]);
+
+    let (vertices, indices) = rect(0.0, -1.0, 1.0, 1.0, BLUE);
+    mesh.append(&vertices, &indices);
+    assert_eq!(mesh.vertex_count(), 8);
+    assert_eq!(mesh.index_count(), 12);
+    renderer.redraw();
+    let frame = renderer.read_frame();
+    assert_eq!(pixel(&frame, left, HEIGHT / 2), [255, 0, 0, 255]);
+    assert_eq!(pixel(&frame, right, HEIGHT / 2), [0, 0, 255, 255]);
+
+    mesh.clear();
+    renderer.redraw();
+    let frame = renderer.read_frame();
+    assert_eq!(pixel(&frame, left, HEIGHT / 2), [0, 255, 0, 255]);
+}
+
+#[test]
+fn grows_buffers() {
+    let mesh = Mesh::new();
+    let renderer = pollster::block_on(Renderer::new_headless(
+        WIDTH,
+        HEIGHT,
+        1.0,
+        MeshScene::new(mesh.clone()),
+    ));
+    renderer.redraw();
+
+    // One thin column per pixel, which doesn't fit in the initial buffers,
+    // appended over several frames.
+    for x in 0..WIDTH {
+        let x0 = x as f32 / WIDTH as f32 * 2.0 - 1.0;
+        let x1 = (x + 1) as f32 / WIDTH as f32 * 2.0 - 1.0;
+        let color = if x % 2 == 0 { RED } else { BLUE };
+        let (vertices, indices) = rect(x0, -1.0, x1, 1.0, color);
+        mesh.append(&vertices, &indices);
+        if x % 16 == 0 {
+            renderer.redraw();
+        }
+    }
+    renderer.redraw();
+
+    let frame = renderer.read_frame();
+    for x in 0..WIDTH {
+        let expected = if x % 2 == 0 {
+            [255, 0, 0, 255]
+        } else {
+            [0, 0, 255, 255]
+        };
+        assert_eq!(pixel(&frame, x, HEIGHT / 2), expected, "x = {x}");
+    }
+}
+
+#[test]
+#[should_panic = "index 3 is out of bounds of 3 vertices"]
+fn index_out_of_bounds() {
+    let vertices = [Vertex::default(); 3];
+    Mesh::new().append(&vertices, &[0, 1, 3]);
+}
@@ -0,0 +1,149 @@
+use std::path::{Path, PathBuf};
+
+use wgpu_objc2_example::model::{Model, ModelError};
+
+fn corpus(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/models")
+        .join(name)
+}
+
+/// Every normal has unit length, and every index is in bounds.
+fn check_mesh_invariants(model: &Model) {
+    for mesh in &model.meshes {
+        assert_eq!(mesh.indices.len() % 3, 0);
+        for &index in &mesh.indices {
+            assert!((index as usize) < mesh.vertices.len());
+        }
+        for vertex in &mesh.vertices {
+            let [x, y, z] = vertex.normal;
+            let length = (x * x + y * y + z * z).sqrt();
+            assert!((length - 1.0).abs() < 1e-4, "{vertex:?}");
+        }
+    }
+}
+
+#[test]
+fn gltf_embedded() {
+    let model = Model::load(corpus("cube.gltf")).unwrap();
+    check_mesh_invariants(&model);
+    assert_eq!(model.meshes.len(), 1);
+    let mesh = &model.meshes[0];
+    assert_eq!(mesh.vertices.len(), 24);
+    assert_eq!(mesh.indices.len(), 36);
+    assert_eq!(mesh.material.base_color, [1.0; 4]);
+    let texture = mesh.material.base_color_texture.as_ref().unwrap();
+    assert_eq!((texture.width, texture.height), (4, 4));
+    assert_eq!(texture.pixels.len(), 4 * 4 * 4);
+    assert_eq!(texture.pixels[..8], [255, 255, 255, 255, 255, 128, 0, 255]);
+
+    // The unit cube is scaled by 2, and then translated by 1 along x.
+    assert_eq!(model.bounds(), Some(([0.0, -1.0, -1.0], [2.0, 1.0, 1.0])));
+    let front = mesh.vertices.iter().find(|v| v.position[2] == 1.0).unwrap();
+    assert_eq!(front.normal, [0.0, 0.0, 1.0]);
+}
+
+#[test]
+fn gltf_separate_files() {
+    let model = Model::load(corpus("quad.gltf")).unwrap();
+    check_mesh_invariants(&model);
+    assert_eq!(model.meshes.len(), 1);
+    let mesh = &model.meshes[0];
+    // Not indexed in the file.
+    assert_eq!(mesh.indices, [0, 1, 2, 3, 4, 5]);
+    assert_eq!(mesh.material.base_color, [1.0, 1.0, 0.5, 1.0]);
+    let texture = mesh.material.base_color_texture.as_ref().unwrap();
+    assert_eq!((texture.width, texture.height), (2, 2));
+    // Computed, since the file has no normals.
+    for vertex in &mesh.vertices {
+        assert_eq!(vertex.normal, [0.0, 0.0, 1.0]);
+    }
+    assert_eq!(mesh.vertices[5].uv, [0.0, 0.0]);
+}
+
+#[test]
+fn gltf_texture_coordinate_set() {
+    let model = Model::load(corpus("tex_coord_1.gltf")).unwrap();
+    check_mesh_invariants(&model);
+    let mesh = &model.meshes[0];
+    assert!(mesh.material.base_color_texture.is_some());
+    // Read from `TEXCOORD_1`, which the texture uses, rather than the zeroes
+    // in `TEXCOORD_0`.
+    let uvs: Vec<_> = mesh.vertices.iter().map(|vertex| vertex.uv).collect();
+    assert_eq!(uvs, [[0.0, 1.0], [1.0, 1.0], [0.0, 0.0]]);
+}
+
+#[test]
+fn gltf_grey_alpha_texture() {
+    let model = Model::load(corpus("grey_alpha.gltf")).unwrap();
+    let texture = model.meshes[0]
+        .material
+        .base_color_texture
+        .as_ref()
+        .unwrap();
+    assert_eq!((texture.width, texture.height), (2, 1));
+    assert_eq!(texture.pixels, [10, 10, 10, 20, 200, 200, 200, 255]);
+}
+
+#[test]
+fn obj_with_materials() {
+    let model = Model::load(corpus("cube.obj")).unwrap();
+    check_mesh_invariants(&model);
+    assert_eq!(model.meshes.len(), 2);
+    let textured = &model.meshes[0];
+    let red = &model.meshes[1];
+    assert_eq!(textured.indices.len(), 5 * 6);
+    assert_eq!(red.indices.len(), 6);
+
+    let texture = textured.material.base_color_texture.as_ref().unwrap();
+    assert_eq!((texture.width, texture.height), (4, 4));
+    assert_eq!(red.material.base_color, [1.0, 0.0, 0.0, 1.0]);
+    assert_eq!(red.material.base_color_texture, None);
+    for vertex in &red.vertices {
+        assert_eq!(vertex.normal, [0.0, 0.0, -1.0]);
+    }
+    // Flipped, since OBJ texture coordinates start at the bottom.
+    let top_left = textured
+        .vertices
+        .iter()
+        .find(|v| v.position == [-0.5, 0.5, 0.5])
+        .unwrap();
+    assert_eq!(top_left.uv, [0.0, 0.0]);
+    assert_eq!(model.bounds(), Some(([-0.5; 3], [0.5; 3])));
+}
+
+#[test]
+fn obj_without_normals() {
+    let model = Model::load(corpus("tetrahedron.obj")).unwrap();
+    check_mesh_invariants(&model);
+    assert_eq!(model.meshes.len(), 1);
+    let mesh = &model.meshes[0];
+    assert_eq!(mesh.vertices.len(), 4);
+    assert_eq!(mesh.indices.len(), 12);
+    assert_eq!(mesh.material.base_color_texture, None);
+    // The bottom vertices are shared with the downwards facing triangle.
+    assert!(mesh.vertices[3].normal[1] < 0.0);
+}
+
+#[test]
+fn errors() {
+    assert!(matches!(
+        Model::load(corpus("quad.bin")),
+        Err(ModelError::UnsupportedExtension(_))
+    ));
+    assert!(matches!(
+        Model::load(corpus("missing.gltf")),
+        Err(ModelError::Gltf(_))
+    ));
+    assert!(matches!(
+        Model::load(corpus("missing.obj")),
+        Err(ModelError::Obj(_))
+    ));
+    assert!(matches!(
+        Model::load(corpus("bad_index.gltf")),
+        Err(ModelError::IndexOutOfBounds {
+            index: 7,
+            vertex_count: 3
+        })
+    ));
+}
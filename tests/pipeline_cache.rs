@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use wgpu_objc2_example::pipeline_cache::{CacheFileContents, CacheFileError, PipelineTimings};
+
+fn contents() -> CacheFileContents {
+    let timings = HashMap::from([
+        (
+            "Rgba8UnormSrgb x4".to_string(),
+            PipelineTimings {
+                cold: Some(Duration::from_micros(12_345)),
+                warm: Some(Duration::from_micros(678)),
+            },
+        ),
+        (
+            "Bgra8Unorm x1".to_string(),
+            PipelineTimings {
+                cold: Some(Duration::from_micros(9_000)),
+                warm: None,
+            },
+        ),
+    ]);
+    CacheFileContents {
+        timings,
+        data: vec![0, 1, 2, 3, 255, b'\n', b'\t'],
+    }
+}
+
+#[test]
+fn round_trip() {
+    let contents = contents();
+    let bytes = contents.to_bytes();
+    assert_eq!(CacheFileContents::from_bytes(&bytes), Ok(contents));
+
+    // The header length is followed by the header, and then the data.
+    let header_length = u32::from_le_bytes(bytes[..4].try_into().unwrap()) as usize;
+    assert_eq!(
+        &bytes[4..4 + header_length],
+        b"Bgra8Unorm x1\t9000\t\nRgba8UnormSrgb x4\t12345\t678\n"
+    );
+    assert_eq!(&bytes[4 + header_length..], [0, 1, 2, 3, 255, b'\n', b'\t']);
+
+    let empty = CacheFileContents::default();
+    assert_eq!(empty.to_bytes(), [0, 0, 0, 0]);
+    assert_eq!(CacheFileContents::from_bytes(&[0, 0, 0, 0]), Ok(empty));
+}
+
+#[test]
+fn truncated() {
+    let bytes = contents().to_bytes();
+    let header_length = u32::from_le_bytes(bytes[..4].try_into().unwrap()) as usize;
+    for length in [0, 3, 4, 4 + header_length - 1] {
+        assert_eq!(
+            CacheFileContents::from_bytes(&bytes[..length]),
+            Err(CacheFileError::Truncated),
+            "{length} bytes"
+        );
+    }
+    // Truncated data is left for the driver to reject.
+    let contents = CacheFileContents::from_bytes(&bytes[..4 + header_length]).unwrap();
+    assert!(contents.data.is_empty());
+}
+
+#[test]
+fn corrupt() {
+    let with_header = |header: &[u8]| {
+        let mut bytes = (header.len() as u32).to_le_bytes().to_vec();
+        bytes.extend_from_slice(header);
+        bytes
+    };
+    for header in [
+        &b"\xff\xfe\t1\t2\n"[..],
+        b"missing durations\n",
+        b"too many\t1\t2\t3\n",
+        b"negative\t-1\t\n",
+        b"not a number\t1.5\t\n",
+    ] {
+        assert_eq!(
+            CacheFileContents::from_bytes(&with_header(header)),
+            Err(CacheFileError::InvalidHeader),
+            "{}",
+            header.escape_ascii()
+        );
+    }
+    // A header length that is much larger than the file.
+    assert_eq!(
+        CacheFileContents::from_bytes(&[255, 255, 255, 255, 0]),
+        Err(CacheFileError::Truncated)
+    );
+}
@@ -0,0 +1,19 @@
+use wgpu::PresentMode::*;
+use wgpu_objc2_example::present_mode::{negotiate, NO_VSYNC, VSYNC};
+
+#[test]
+fn first_supported_preference() {
+    assert_eq!(negotiate(NO_VSYNC, &[Fifo, Immediate, Mailbox]), Mailbox);
+    assert_eq!(
+        negotiate(NO_VSYNC, &[Fifo, FifoRelaxed, Immediate]),
+        Immediate
+    );
+    assert_eq!(negotiate(NO_VSYNC, &[Fifo, FifoRelaxed]), FifoRelaxed);
+    assert_eq!(negotiate(VSYNC, &[Fifo, Immediate, Mailbox]), Fifo);
+}
+
+#[test]
+fn falls_back_to_fifo() {
+    assert_eq!(negotiate(&[Mailbox], &[Fifo, Immediate]), Fifo);
+    assert_eq!(negotiate(&[], &[Fifo]), Fifo);
+}
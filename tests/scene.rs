@@ -0,0 +1,159 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use wgpu_objc2_example::gpu_context::GpuContext;
+use wgpu_objc2_example::render_target::OffscreenTexture;
+use wgpu_objc2_example::renderer::Renderer;
+use wgpu_objc2_example::scene::{Scene, TargetFormat};
+use wgpu_objc2_example::wgpu_triangle::Triangle;
+
+/// The calls made to a [`RecordingScene`].
+#[derive(Debug, Clone, PartialEq)]
+enum Call {
+    Init(TargetFormat),
+    Resize(u32, u32, f32),
+    Update,
+    Render,
+}
+
+/// A scene that draws nothing, and records how it is called.
+#[derive(Debug, Clone, Default)]
+struct RecordingScene {
+    calls: Arc<Mutex<Vec<Call>>>,
+    dts: Arc<Mutex<Vec<Duration>>>,
+}
+
+impl RecordingScene {
+    fn take_calls(&self) -> Vec<Call> {
+        std::mem::take(&mut self.calls.lock().unwrap())
+    }
+}
+
+impl Scene for RecordingScene {
+    fn init(&mut self, _gpu: &GpuContext, format: TargetFormat) {
+        self.calls.lock().unwrap().push(Call::Init(format));
+    }
+
+    fn resize(&mut self, _gpu: &GpuContext, width: u32, height: u32, scale_factor: f32) {
+        let resize = Call::Resize(width, height, scale_factor);
+        self.calls.lock().unwrap().push(resize);
+    }
+
+    fn update(&mut self, _gpu: &GpuContext, dt: Duration) {
+        self.calls.lock().unwrap().push(Call::Update);
+        self.dts.lock().unwrap().push(dt);
+    }
+
+    fn render(&mut self, _rpass: &mut wgpu::RenderPass<'_>) {
+        self.calls.lock().unwrap().push(Call::Render);
+    }
+}
+
+#[test]
+fn hooks() {
+    let scene = RecordingScene::default();
+    let renderer = pollster::block_on(Renderer::new_headless(64, 48, 2.0, scene.clone()));
+    let format = renderer.target_format();
+    assert_eq!(
+        scene.take_calls(),
+        [Call::Init(format), Call::Resize(64, 48, 2.0)]
+    );
+
+    renderer.redraw();
+    std::thread::sleep(Duration::from_millis(10));
+    renderer.redraw();
+    assert_eq!(
+        scene.take_calls(),
+        [Call::Update, Call::Render, Call::Update, Call::Render]
+    );
+    let dts = scene.dts.lock().unwrap().clone();
+    assert_eq!(dts[0], Duration::ZERO);
+    assert!(dts[1] >= Duration::from_millis(10), "{dts:?}");
+
+    renderer.resize(32, 24, 1.0);
+    assert_eq!(scene.take_calls(), [Call::Resize(32, 24, 1.0)]);
+
+    // The pipelines must be recreated for the new format.
+    renderer.set_depth_format(None).unwrap();
+    let format = TargetFormat {
+        depth_format: None,
+        ..format
+    };
+    assert_eq!(renderer.target_format(), format);
+    assert_eq!(
+        scene.take_calls(),
+        [Call::Init(format), Call::Resize(32, 24, 1.0)]
+    );
+}
+
+#[test]
+fn clears_without_drawing() {
+    let renderer = pollster::block_on(Renderer::new_headless(4, 4, 1.0, RecordingScene::default()));
+    renderer.set_clear_color(wgpu::Color::BLUE);
+    renderer.redraw();
+    for pixel in renderer.read_frame().chunks(4) {
+        assert_eq!(pixel, [0, 0, 255, 255]);
+    }
+}
+
+#[test]
+fn replace_scene() {
+    let renderer = pollster::block_on(Renderer::new_headless(
+        64,
+        48,
+        1.0,
+        RecordingScene::default(),
+    ));
+    renderer.redraw();
+    let before = renderer.read_frame();
+
+    renderer.set_scene(Triangle::new());
+    renderer.redraw();
+    assert_ne!(renderer.read_frame(), before, "the triangle was not drawn");
+}
+
+/// A triangle that shares the pipeline it was initialized with.
+#[derive(Debug, Default)]
+struct PipelineProbe {
+    triangle: Triangle,
+    render_pipeline: Arc<Mutex<Option<wgpu::RenderPipeline>>>,
+}
+
+impl Scene for PipelineProbe {
+    fn init(&mut self, gpu: &GpuContext, format: TargetFormat) {
+        self.triangle.init(gpu, format);
+        *self.render_pipeline.lock().unwrap() = self.triangle.render_pipeline().cloned();
+    }
+
+    fn resize(&mut self, gpu: &GpuContext, width: u32, height: u32, scale_factor: f32) {
+        self.triangle.resize(gpu, width, height, scale_factor);
+    }
+
+    fn update(&mut self, gpu: &GpuContext, dt: Duration) {
+        self.triangle.update(gpu, dt);
+    }
+
+    fn render(&mut self, rpass: &mut wgpu::RenderPass<'_>) {
+        self.triangle.render(rpass);
+    }
+}
+
+#[test]
+fn triangles_share_pipelines() {
+    let gpu = Arc::new(pollster::block_on(GpuContext::new_headless()));
+    let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+    let renderer = |width, height| {
+        let probe = PipelineProbe::default();
+        let render_pipeline = Arc::clone(&probe.render_pipeline);
+        let target = OffscreenTexture::new(gpu.device(), format, width, height);
+        let renderer = Renderer::from_target(Arc::clone(&gpu), target, 1.0, probe);
+        let render_pipeline = render_pipeline.lock().unwrap().clone().unwrap();
+        (renderer, render_pipeline)
+    };
+    let (first, first_pipeline) = renderer(64, 48);
+    let (second, second_pipeline) = renderer(32, 24);
+    assert_eq!(first_pipeline, second_pipeline);
+
+    first.redraw();
+    second.redraw();
+}
@@ -0,0 +1,62 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tracing_subscriber::layer::SubscriberExt;
+use wgpu_objc2_example::renderer::Renderer;
+use wgpu_objc2_example::span_timing::{SpanSummary, SpanTimingLayer};
+use wgpu_objc2_example::wgpu_triangle::Triangle;
+
+/// A subscriber that collects every summary, reported whenever a span closes.
+fn subscriber() -> (impl tracing::Subscriber, Arc<Mutex<Vec<SpanSummary>>>) {
+    let summaries = Arc::new(Mutex::new(Vec::new()));
+    let layer = SpanTimingLayer::with_reporter(Duration::ZERO, {
+        let summaries = Arc::clone(&summaries);
+        move |report: &[SpanSummary]| summaries.lock().unwrap().extend_from_slice(report)
+    });
+    (tracing_subscriber::registry().with(layer), summaries)
+}
+
+#[test]
+fn only_counts_entered_time() {
+    let (subscriber, summaries) = subscriber();
+    tracing::subscriber::with_default(subscriber, || {
+        let span = tracing::info_span!("outer");
+        std::thread::sleep(Duration::from_millis(200));
+        span.in_scope(|| std::thread::sleep(Duration::from_millis(5)));
+    });
+
+    let summaries = summaries.lock().unwrap();
+    assert_eq!(summaries.len(), 1);
+    let summary = summaries[0];
+    assert_eq!(summary.name, "outer");
+    assert_eq!(summary.count, 1);
+    assert!(summary.total >= Duration::from_millis(5), "{summary:?}");
+    // Far below the time spent outside the span, to allow for slow wakeups.
+    assert!(summary.total < Duration::from_millis(150), "{summary:?}");
+    assert_eq!(summary.max, summary.total);
+}
+
+#[test]
+fn redraw_stages() {
+    let triangle = pollster::block_on(Renderer::new_headless(64, 48, 1.0, Triangle::new()));
+    let (subscriber, summaries) = subscriber();
+    tracing::subscriber::with_default(subscriber, || {
+        triangle.resize(32, 24, 1.0);
+        triangle.redraw();
+        triangle.redraw();
+    });
+
+    let summaries = summaries.lock().unwrap();
+    let count = |name| {
+        summaries
+            .iter()
+            .filter(|summary| summary.name == name)
+            .map(|summary| summary.count)
+            .sum::<u32>()
+    };
+    assert_eq!(count("resize"), 1);
+    assert_eq!(count("redraw"), 2);
+    for stage in ["acquire", "encode", "submit", "present"] {
+        assert_eq!(count(stage), 2, "{stage}");
+    }
+}
@@ -0,0 +1,124 @@
+use std::path::PathBuf;
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use wgpu_objc2_example::frame_counter::{FrameCounter, FrameTimes};
+use wgpu_objc2_example::stats_sink::{CsvSink, FrameStats, JsonLinesSink, MemorySink, StatsSink};
+
+fn stats() -> FrameStats {
+    let mut frame_times = FrameTimes::new(10);
+    frame_times.push(Duration::from_millis(10));
+    frame_times.push(Duration::from_millis(20));
+    FrameStats {
+        elapsed: Duration::from_millis(1500),
+        frame_count: 3,
+        fps: 2.0,
+        frame_latency: Some(2),
+        gpu_ms: None,
+        frame_times: frame_times.stats(2, Duration::from_millis(10)),
+    }
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("stats_sink");
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+#[test]
+fn csv() {
+    let path = temp_path("stats.csv");
+    let mut sink = CsvSink::create(&path).unwrap();
+    sink.report(&stats());
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "elapsed_ms,frame_count,fps,frame_latency,gpu_ms,min_ms,avg_ms,p50_ms,p95_ms,p99_ms,max_ms,hitches\n\
+         1500,3,2,2,,10,15,10,20,20,20,1\n"
+    );
+}
+
+#[test]
+fn json_lines() {
+    let path = temp_path("stats.jsonl");
+    let mut sink = JsonLinesSink::create(&path).unwrap();
+    sink.report(&stats());
+    sink.report(&stats());
+    let contents = std::fs::read_to_string(&path).unwrap();
+    let line = "{\"elapsed_ms\":1500,\"frame_count\":3,\"fps\":2,\"frame_latency\":2,\"gpu_ms\":null,\
+                \"min_ms\":10,\"avg_ms\":15,\"p50_ms\":10,\"p95_ms\":20,\"p99_ms\":20,\"max_ms\":20,\"hitches\":1}";
+    assert_eq!(contents, format!("{line}\n{line}\n"));
+}
+
+#[test]
+fn memory_reports_from_counter() {
+    let sink = MemorySink::new();
+    let frame_counter = FrameCounter::with_sink(Box::new(sink.clone()));
+    // Every interval is shorter than a refresh, so none are hitches.
+    frame_counter.set_refresh_interval(Duration::from_secs(1));
+    for _ in 0..3 {
+        frame_counter.update();
+    }
+    // Reported by the helper thread, even though no more frames are rendered.
+    frame_counter.set_report_interval(Duration::ZERO);
+    let start = Instant::now();
+    let report = loop {
+        if let Some(report) = sink.reports().first() {
+            break *report;
+        }
+        assert!(start.elapsed() < Duration::from_secs(30), "no report");
+        thread::sleep(Duration::from_millis(10));
+    };
+    drop(frame_counter);
+
+    assert_eq!(report.frame_count, 3);
+    assert_eq!(report.frame_times.unwrap().hitches, 0);
+}
+
+#[test]
+fn drop_is_prompt() {
+    let frame_counter = FrameCounter::with_sink(Box::new(MemorySink::new()));
+    let start = Instant::now();
+    drop(frame_counter);
+    // The reporter thread is stopped rather than left running, with plenty of
+    // leeway for a loaded machine.
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+/// A sink that blocks in `report` until told to continue.
+#[derive(Debug)]
+struct BlockingSink {
+    entered: mpsc::Sender<()>,
+    release: Mutex<mpsc::Receiver<()>>,
+}
+
+impl StatsSink for BlockingSink {
+    fn report(&mut self, _stats: &FrameStats) {
+        // Either end may be gone once the test is done with the sink.
+        let _ = self.entered.send(());
+        let _ = self.release.lock().unwrap().recv();
+    }
+}
+
+#[test]
+fn update_does_not_wait_for_sink() {
+    let (entered, entered_receiver) = mpsc::channel();
+    let (release, release_receiver) = mpsc::channel();
+    let frame_counter = FrameCounter::with_sink(Box::new(BlockingSink {
+        entered,
+        release: Mutex::new(release_receiver),
+    }));
+    frame_counter.set_report_interval(Duration::ZERO);
+    entered_receiver
+        .recv_timeout(Duration::from_secs(30))
+        .expect("no report");
+
+    // While the sink is blocked, frames are still recorded.
+    for _ in 0..3 {
+        frame_counter.update();
+    }
+    // Unblock this and any further reports, so that the reporter thread can
+    // stop.
+    drop(release);
+    drop(frame_counter);
+}
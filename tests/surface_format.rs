@@ -0,0 +1,50 @@
+use wgpu::TextureFormat::*;
+use wgpu_objc2_example::surface_format::{FormatPreference, SurfaceFormat};
+
+fn choose(formats: &[wgpu::TextureFormat], preference: FormatPreference) -> SurfaceFormat {
+    SurfaceFormat::choose(formats, preference).unwrap()
+}
+
+#[test]
+fn prefers_srgb() {
+    let format = choose(&[Bgra8Unorm, Bgra8UnormSrgb], FormatPreference::Srgb);
+    assert_eq!(format.format, Bgra8UnormSrgb);
+    assert_eq!(format.view_format, Bgra8UnormSrgb);
+    assert!(format.view_formats().is_empty());
+}
+
+#[test]
+fn srgb_view_of_linear_surface() {
+    let format = choose(&[Rgb10a2Unorm, Bgra8Unorm], FormatPreference::Srgb);
+    assert_eq!(format.format, Bgra8Unorm);
+    assert_eq!(format.view_format, Bgra8UnormSrgb);
+    assert_eq!(format.view_formats(), [Bgra8UnormSrgb]);
+}
+
+#[test]
+fn no_srgb_at_all() {
+    let format = choose(&[Rgb10a2Unorm], FormatPreference::Srgb);
+    assert_eq!(format.format, Rgb10a2Unorm);
+    assert_eq!(format.view_format, Rgb10a2Unorm);
+}
+
+#[test]
+fn extended_range() {
+    let formats = [Bgra8UnormSrgb, Rgba16Float];
+    assert_eq!(
+        choose(&formats, FormatPreference::Srgb).format,
+        Bgra8UnormSrgb
+    );
+    let format = choose(&formats, FormatPreference::ExtendedRange);
+    assert_eq!(format.format, Rgba16Float);
+    assert_eq!(format.view_format, Rgba16Float);
+
+    // Falls back to sRGB if not supported.
+    let format = choose(&[Bgra8Unorm], FormatPreference::ExtendedRange);
+    assert_eq!(format.view_format, Bgra8UnormSrgb);
+}
+
+#[test]
+fn empty() {
+    assert_eq!(SurfaceFormat::choose(&[], FormatPreference::Srgb), None);
+}
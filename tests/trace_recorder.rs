@@ -0,0 +1,59 @@
+//! The recorder is global, so this is its only test.
+use std::path::PathBuf;
+use std::thread;
+
+use wgpu_objc2_example::renderer::Renderer;
+use wgpu_objc2_example::trace_recorder;
+use wgpu_objc2_example::wgpu_triangle::Triangle;
+
+#[test]
+fn records_spans() {
+    // Not recorded, since recording has not started yet.
+    drop(trace_recorder::span("before_start"));
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("trace_recorder");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("trace.json");
+    trace_recorder::start(&path).unwrap();
+    assert!(trace_recorder::is_recording());
+    assert!(trace_recorder::start(&path).is_err());
+
+    let triangle = pollster::block_on(Renderer::new_headless(64, 48, 1.0, Triangle::new()));
+    triangle.resize(32, 24, 1.0);
+    triangle.redraw();
+    thread::Builder::new()
+        .name("helper \"thread\"".into())
+        .spawn(|| drop(trace_recorder::span("on_helper")))
+        .unwrap()
+        .join()
+        .unwrap();
+
+    trace_recorder::finish().unwrap();
+    assert!(!trace_recorder::is_recording());
+    drop(trace_recorder::span("after_finish"));
+
+    let trace = std::fs::read_to_string(&path).unwrap();
+    assert!(trace.starts_with("[\n"), "{trace}");
+    assert!(trace.ends_with("\n]\n"), "{trace}");
+    assert!(!trace.contains(",\n]"), "trailing comma: {trace}");
+    for name in [
+        "resize",
+        "redraw",
+        "acquire",
+        "encode",
+        "submit",
+        "present",
+        "on_helper",
+    ] {
+        assert!(
+            trace.contains(&format!(r#"{{"name":"{name}","ph":"X""#)),
+            "missing {name}: {trace}"
+        );
+    }
+    assert!(
+        trace.contains(r#""args":{"name":"helper \"thread\""}"#),
+        "{trace}"
+    );
+    assert!(!trace.contains("before_start"));
+    assert!(!trace.contains("after_finish"));
+}